@@ -0,0 +1,66 @@
+use access_control::data_access1::{
+    field_info_map, Clock, Dialect, EvalContext, FieldInfo, FieldType, FilterLimits, FilterNode,
+    Sql,
+};
+use chrono::FixedOffset;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
+
+// benches 是单独编译的，看不到 `#[cfg(test)]` 的 `test_info`，只能自己写一份
+fn infos() -> HashMap<String, FieldInfo> {
+    let info = |name: &str, type_| FieldInfo {
+        name: name.into(),
+        text: "".into(),
+        nullable: true,
+        type_,
+        operation: " Equal Cmp In ".into(),
+        sortable: false,
+        aggregatable: false,
+    };
+    field_info_map(vec![
+        info("id", FieldType::Id),
+        info("age", FieldType::Int),
+        info("city", FieldType::Str),
+        info("score", FieldType::Num),
+        info("reg_time", FieldType::DateTime),
+    ])
+}
+
+/// Display 遇到 Like 会 panic (`StrValue::escaped_like` 还没有实现)，也不支持
+/// current_user_id 等值，只用两边都支持的条件，输出中的分组名 `without_like` 标明这一点
+const FILTER: &str = "age >= 18 and (city in ('北京', '上海', '广州', '深圳') or score > 60.5) \
+    and reg_time between ['2022-01-01 00:00:00', '2022-02-01 00:00:00') \
+    and not (id in (1, 2, 3, 4, 5, 6, 7, 8) or city = '杭州')";
+
+fn bench(c: &mut Criterion) {
+    let infos = infos();
+    let clock = Clock::system(FixedOffset::east_opt(8 * 3600).unwrap());
+    let ctx = EvalContext {
+        infos: &infos,
+        user_id: 1,
+        clock: &clock,
+    };
+    let limits = FilterLimits::default();
+    let node = FilterNode::parse(FILTER, &infos).unwrap();
+
+    let mut c = c.benchmark_group("without_like");
+    c.bench_function("display", |b| b.iter(|| black_box(&node).to_string()));
+    c.bench_function("to_sql", |b| {
+        b.iter(|| {
+            black_box(&node)
+                .to_sql(Dialect::Postgres, &ctx, &limits)
+                .unwrap()
+        })
+    });
+    let mut sql = Sql::new(Dialect::Postgres);
+    c.bench_function("write_sql_reuse", |b| {
+        b.iter(|| {
+            sql.clear();
+            black_box(&node).write_sql(&mut sql, &ctx, &limits).unwrap();
+        })
+    });
+    c.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
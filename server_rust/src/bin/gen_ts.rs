@@ -0,0 +1,8 @@
+use access_control::ts::{dts, DTS_PATH};
+use std::path::Path;
+
+fn main() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(DTS_PATH);
+    std::fs::write(&path, dts()).unwrap();
+    println!("{}", path.display());
+}
@@ -1,8 +1,11 @@
+//! 旧版过滤条件，新代码使用 [`crate::data_access1::FilterNode`]，
+//! 保存的旧数据通过 [`crate::data_access1::VersionedFilter::from_json`] 迁移
+
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 fn slice_join<T: Display>(f: &mut Formatter<'_>, v: &[T], separator: &str) -> FmtResult {
-    if v.len() == 0 {
+    if v.is_empty() {
         return Ok(());
     }
     write!(f, "{}", v[0])?;
@@ -32,7 +35,7 @@ pub enum LogicalNode {
     Lte(Field, ValueNode),
     In(Field, ListNode),
     NotIn(Field, ListNode),
-    // Not(Box<LogicalNode>), 可以不提供
+    Not(Box<LogicalNode>),
 }
 impl Display for LogicalNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -48,8 +51,12 @@ impl Display for LogicalNode {
             Gte(l, r) => write!(f, "({} >= {})", l, r),
             Lt(l, r) => write!(f, "({} < {})", l, r),
             Lte(l, r) => write!(f, "({} <= {})", l, r),
+            // `IN ()` 不是合法的 sql，空列表 In 为 FALSE，NotIn 为 TRUE
+            In(_, r) if r.is_empty() => write!(f, "(1 = 0)"),
+            NotIn(_, r) if r.is_empty() => write!(f, "(1 = 1)"),
             In(l, r) => write!(f, "({} IN{})", l, r),
             NotIn(l, r) => write!(f, "({} NOT IN{})", l, r),
+            Not(v) => write!(f, "(NOT {})", v),
             // _ => todo!(),
         }
     }
@@ -63,7 +70,7 @@ pub enum ValueNode {
     Num(f64),
     DateTime(String),
     UnixTimestamp(u64),
-    // Field(String),
+    Field(String),
     // Null, // 应该使用 IsNull, IsNotNull
     CurrentUserId,
     CurrentTime,
@@ -77,7 +84,7 @@ impl Display for ValueNode {
             Str(v) | DateTime(v) => write!(f, "'{}'", v),
             Int(v) => write!(f, "{}", v),
             Num(v) => write!(f, "{}", v),
-            // Field(v) => write!(f, "[{}]", v),
+            Field(v) => write!(f, "[{}]", v),
             UnixTimestamp(v) => write!(f, "{}", v),
             CurrentUserId => write!(f, "@CurrentUserId"),
             _ => todo!(), // 由数据库 sql 确定
@@ -86,11 +93,11 @@ impl Display for ValueNode {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Field(String);
+pub struct Field(pub String);
 
 impl Display for Field {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[{}]", self)
+        write!(f, "[{}]", self.0)
     }
 }
 
@@ -128,12 +135,22 @@ pub enum ListNode {
     NumList(Vec<f64>),
 }
 
+impl ListNode {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            ListNode::StrList(v) => v.is_empty(),
+            ListNode::IntList(v) => v.is_empty(),
+            ListNode::NumList(v) => v.is_empty(),
+        }
+    }
+}
+
 impl Display for ListNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use ListNode::*;
         match self {
             StrList(v) => {
-                if v.len() == 0 {
+                if v.is_empty() {
                     write!(f, "()")
                 } else {
                     write!(f, "('")?;
@@ -149,7 +166,7 @@ impl Display for ListNode {
 
 #[cfg(test)]
 mod tests {
-    use super::{Field, ListNode::*, LogicalNode::*, ValueNode::*};
+    use super::{Field, ListNode::*, LogicalNode::*, ValueNode, ValueNode::*};
     #[test]
     fn and_or() {
         let root = &And(vec![
@@ -188,12 +205,49 @@ mod tests {
         assert_eq!(root.to_string(), "([id] IN('a', 'b'))");
     }
 
+    #[test]
+    fn empty_list() {
+        let root = In(Field("id".to_string()), StrList(vec![]));
+        assert_eq!(root.to_string(), "(1 = 0)");
+        let root = NotIn(Field("id".to_string()), IntList(vec![]));
+        assert_eq!(root.to_string(), "(1 = 1)");
+    }
+
     #[test]
     fn not_in_ist() {
         let root = NotIn(Field("id".to_string()), IntList(vec![1, 2, 3]));
         assert_eq!(root.to_string(), "([id] NOT IN(1, 2, 3))");
     }
 
+    #[test]
+    fn not() {
+        let root = &Not(Box::new(Or(vec![
+            Eq(Field("tag".to_string()), Str("A".to_string())),
+            Eq(Field("status".to_string()), Int(2)),
+        ])));
+        assert_eq!(root.to_string(), "(NOT (([tag] = 'A') OR ([status] = 2)))");
+        let json = serde_json::to_string(root).unwrap();
+        assert!(json.starts_with(r#"{"not":{"or":"#));
+    }
+
+    #[test]
+    fn field_value() {
+        let root = &And(vec![
+            Gt(Field("updated_at".to_string()), ValueNode::Field("created_at".to_string())),
+            Eq(Field("assigned_to".to_string()), CurrentUserId),
+        ]);
+        let res = "(([updated_at] > [created_at]) AND ([assigned_to] = @CurrentUserId))";
+        assert_eq!(root.to_string(), res);
+    }
+
+    #[test]
+    fn field_display() {
+        // 曾经写成 `write!(f, "[{}]", self)`，无限递归导致栈溢出
+        assert_eq!(Field("name".to_string()).to_string(), "[name]");
+        let root = IsNull(Field("name".to_string()));
+        assert_eq!(root.to_string(), "([name] IS NULL)");
+    }
+
     #[test]
     fn value_node_literal() {
         let root = &Eq(Field("time".to_string()), CurrentTime);
@@ -2,9 +2,54 @@ use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use DataAccessErr::*;
 
+mod analyze;
+mod cache;
+mod compose;
+mod cursor;
+mod decimal;
+mod es;
+mod eval;
+mod expr;
+mod limits;
+mod migrate;
+mod mongo;
+mod query;
+mod report;
+mod schema;
+mod sort;
+mod sql;
+mod text;
+mod time;
+mod ts;
+
+pub use analyze::{Warning, WarningKind};
+pub use cache::SqlCache;
+pub use cursor::{Cursor, CursorKey};
+pub use decimal::Decimal;
+pub use es::es_mapping;
+pub use eval::{EvalContext, Record};
+pub use expr::{FilterExpr, ParseErr, ParseErrKind};
+pub use limits::{FilterLimits, LimitExceeded, LimitKind};
+pub use migrate::{VersionedFilter, FILTER_VERSION};
+pub use query::{ListQuery, Page};
+pub use report::{AggFn, Aggregate, ReportQuery};
+pub use schema::filter_schema;
+pub use sort::{Direction, Nulls, SortItem, SortSpec};
+pub use sql::{Bind, Binds, Dialect, Sql};
+pub use text::{EnumLabels, FilterText, Lang};
+pub use time::{parse_datetime, Clock, RelativeTime, TimeUnit, DATETIME_FORMAT};
+pub use ts::filter_dts;
+pub(crate) use ts::ts_interface;
+
+#[derive(Debug, PartialEq)]
 pub enum DataAccessErr {
     TypeErr,
     InvalidOperation,
+    UnknownField,
+    /// json 格式错误、版本号不支持、游标无效或时间不是 ISO-8601 格式
+    InvalidFormat,
+    /// 超过 [`FilterLimits`]
+    TooComplex(LimitExceeded),
 }
 
 fn slice_join<T: Display, S: Display + ?Sized>(
@@ -12,7 +57,7 @@ fn slice_join<T: Display, S: Display + ?Sized>(
     v: &[T],
     separator: &S,
 ) -> FmtResult {
-    if v.len() == 0 {
+    if v.is_empty() {
         return Ok(());
     }
     write!(f, "{}", v[0])?;
@@ -30,37 +75,82 @@ fn slice_join_bracket<T: Display, S: Display + ?Sized>(
     write!(f, ")")
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum FilterNode {
     Logical(Logical, Vec<FilterNode>),
+    Not(Not, Box<FilterNode>),
     Nullable(Nullable, Field),
     Equal(Eq, Field, Value),
     Cmp(Cmp, Field, Value),
+    Between(Between, Field, Value, Value),
     In(In, Field, ListValue),
     Like(Like, Field, StrValue),
 }
 impl FilterNode {
     pub fn check_operation(&self, info: &FieldInfo) -> Result<(), DataAccessErr> {
         let ope = match self {
-            Self::Cmp(..) => " Cmp ",
+            Self::Cmp(..) | Self::Between(..) => " Cmp ",
             Self::Equal(..) => " Equal ",
             Self::In(..) => " In ",
             Self::Like(..) => " Like ",
-            Self::Logical(..) => return Ok(()),
-            Self::Nullable(..) => return info.nullable.then(|| ()).ok_or(InvalidOperation),
+            Self::Logical(..) | Self::Not(..) => return Ok(()),
+            Self::Nullable(..) => return info.nullable.then_some(()).ok_or(InvalidOperation),
         };
         let has = info.operation.find(ope);
         has.and(Some(())).ok_or(InvalidOperation)
     }
+
+    /// 递归检查字段是否存在、操作是否允许、值类型是否匹配
+    pub fn check(&self, infos: &HashMap<String, FieldInfo>) -> Result<(), DataAccessErr> {
+        let field = match self {
+            Self::Logical(_, v) => return v.iter().try_for_each(|n| n.check(infos)),
+            Self::Not(_, n) => return n.check(infos),
+            Self::Nullable(_, field)
+            | Self::Equal(_, field, _)
+            | Self::Cmp(_, field, _)
+            | Self::Between(_, field, ..)
+            | Self::In(_, field, _)
+            | Self::Like(_, field, _) => field,
+        };
+        let info = infos.get(&field.0).ok_or(UnknownField)?;
+        self.check_operation(info)?;
+        match self {
+            Self::Equal(_, _, v) | Self::Cmp(_, _, v) => v.check_field_type(&info.type_, infos),
+            Self::Between(_, _, lo, hi) => {
+                lo.check_field_type(&info.type_, infos)?;
+                hi.check_field_type(&info.type_, infos)
+            }
+            Self::In(_, _, v) => v.chekc_type(&info.type_),
+            Self::Like(..) => match info.type_ {
+                FieldType::Str => Ok(()),
+                _ => Err(TypeErr),
+            },
+            _ => Ok(()),
+        }
+    }
 }
+/// sql 文本，不知道字段类型，值原样输出；时间值要先经过 [`FilterNode::resolve_time`]
+/// 转为字段的类型，见 [`FilterNode::to_sql_text`]
 impl Display for FilterNode {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
             Self::Logical(ope, v) => slice_join_bracket(f, v, ope),
+            Self::Not(ope, n) => match **n {
+                Self::Logical(..) | Self::Nullable(..) => write!(f, "{} {}", ope, n),
+                _ => write!(f, "{} ({})", ope, n),
+            },
             Self::Nullable(ope, field) => write!(f, "({} {})", field, ope),
             Self::Cmp(ope, field, v) => write!(f, "{} {} {}", field, ope, v),
+            Self::Between(ope, field, lo, hi) => match ope {
+                Between::Between => write!(f, "{} BETWEEN {} AND {}", field, lo, hi),
+                Between::GtEqLt => write!(f, "({} >= {} AND {} < {})", field, lo, field, hi),
+                Between::GtLtEq => write!(f, "({} > {} AND {} <= {})", field, lo, field, hi),
+            },
             Self::Equal(ope, field, v) => write!(f, "{} {} {}", field, ope, v),
+            // `IN ()` 不是合法的 sql
+            Self::In(In::In, _, v) if v.is_empty() => write!(f, "1 = 0"),
+            Self::In(In::NotIn, _, v) if v.is_empty() => write!(f, "1 = 1"),
             Self::In(ope, field, v) => write!(f, "{} {}{}", field, ope, v),
             Self::Like(ope, field, v) => match ope {
                 Like::StartWith => write!(f, "{} LIKE '{}%'", field, v.escaped_like()),
@@ -71,7 +161,20 @@ impl Display for FilterNode {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+impl FilterNode {
+    /// 检查后把时间值转为字段的类型和格式，再输出 sql 文本，
+    /// 避免 `DateTime` 字段和时间戳比较、`UnixTiemstamp` 字段和时间字符串比较
+    pub fn to_sql_text(&self, ctx: &EvalContext) -> Result<String, DataAccessErr> {
+        use std::fmt::Write;
+        self.check(ctx.infos)?;
+        let node = self.clone().resolve_time(ctx.clock, ctx.infos)?;
+        let mut s = String::new();
+        write!(s, "{}", node).map_err(|_| InvalidOperation)?;
+        Ok(s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Logical {
     And,
     Or,
@@ -85,7 +188,17 @@ impl Display for Logical {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Not {
+    Not,
+}
+impl Display for Not {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "NOT")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Field(String);
 impl Display for Field {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
@@ -93,7 +206,7 @@ impl Display for Field {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Nullable {
     IsNull,
     IsNotNull,
@@ -107,7 +220,7 @@ impl Display for Nullable {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Cmp {
     // Eq,
     // NotEq,
@@ -129,7 +242,28 @@ impl Display for Cmp {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// 范围，左右边界都要和字段类型一致
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Between {
+    /// 闭区间 `[lo, hi]`
+    Between,
+    /// 左闭右开 `[lo, hi)`，日期范围常用
+    GtEqLt,
+    /// 左开右闭 `(lo, hi]`
+    GtLtEq,
+}
+impl Between {
+    /// 拆成上下界两个比较运算符
+    pub fn bounds(&self) -> (Cmp, Cmp) {
+        match self {
+            Between::Between => (Cmp::GtEq, Cmp::LtEq),
+            Between::GtEqLt => (Cmp::GtEq, Cmp::Lt),
+            Between::GtLtEq => (Cmp::Gt, Cmp::LtEq),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum In {
     In,
     NotIn,
@@ -143,7 +277,7 @@ impl Display for In {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Eq {
     Eq,
     NotEq,
@@ -157,14 +291,14 @@ impl Display for Eq {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Like {
     StartWith,
     Contains,
     EndWith,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StrValue(String);
 impl StrValue {
     fn escaped(&self) -> String {
@@ -181,31 +315,58 @@ impl Display for StrValue {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
 pub enum Value {
     Id(u64), // Id 类型应该只能进行 eq, in 操作
     Int(u64),
     Num(f64),
+    /// 定点小数，json 中可以是字符串或数字，精确比较和绑定
+    Decimal(Decimal),
     Str(StrValue),
     DateTime(StrValue),
     UnixTiemstamp(u64),
     CurrentUserId,
     CurrentDate,
     CurrentTime,
+    /// 相对时间，编译时通过 [`FilterNode::resolve_time`] 转为具体时间
+    RelativeTime(RelativeTime),
+    /// 引用另一个字段，例如 `updated_at > created_at`
+    Field(Field),
 }
 impl Value {
+    /// 同 [`Value::chekc_type`]，[`Value::Field`] 要求引用的字段存在且类型相同
+    pub fn check_field_type(
+        &self,
+        ftype: &FieldType,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<(), DataAccessErr> {
+        match self {
+            Value::Field(field) => {
+                let info = infos.get(&field.0).ok_or(UnknownField)?;
+                (info.type_ == *ftype).then_some(()).ok_or(TypeErr)
+            }
+            v => v.chekc_type(ftype),
+        }
+    }
+
     pub fn chekc_type(&self, ftype: &FieldType) -> Result<(), DataAccessErr> {
         use FieldType as FT;
         match (self, ftype) {
-            (Value::CurrentDate | Value::CurrentTime, FT::DateTime | FT::UnixTiemstamp)
+            (
+                Value::CurrentDate | Value::CurrentTime | Value::RelativeTime(_),
+                FT::DateTime | FT::UnixTiemstamp,
+            )
             | (Value::Id(_) | Value::CurrentUserId, FT::Id)
-            | (Value::Int(_), FieldType::Enum(_))
+            // 枚举的值可以是整数或字符串，见 [`ListValue::chekc_type`]
+            | (Value::Int(_) | Value::Str(_), FieldType::Enum(_))
             | (Value::Str(_), FieldType::Str)
             | (Value::Int(_), FieldType::Int)
             | (Value::Num(_), FieldType::Num)
-            | (Value::DateTime(_), FieldType::DateTime)
-            | (Value::UnixTiemstamp(_), FieldType::UnixTiemstamp) => Ok(()),
+            | (Value::Decimal(_), FT::Decimal { .. })
+            | (Value::UnixTiemstamp(_), FT::DateTime | FT::UnixTiemstamp) => Ok(()),
+            // 时间字符串和时间戳可以互相转换，字符串要是 ISO-8601 格式
+            (Value::DateTime(s), FT::DateTime | FT::UnixTiemstamp) => check_datetime(&s.0),
             _ => Err(DataAccessErr::TypeErr),
         }
     }
@@ -218,35 +379,101 @@ impl Display for Value {
             Id(v) | Int(v) | UnixTiemstamp(v) => write!(f, "{}", v),
             Str(v) | DateTime(v) => write!(f, "{}", v),
             Num(v) => write!(f, "{}", v),
+            Decimal(v) => write!(f, "{}", v),
+            Field(v) => write!(f, "{}", v),
+            // `now/d-30d` 不是合法的 sql，要先经过 [`FilterNode::resolve_time`]
+            RelativeTime(_) => Err(std::fmt::Error),
             CurrentUserId | CurrentDate | CurrentTime => todo!(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+fn check_datetime(s: &str) -> Result<(), DataAccessErr> {
+    let utc = chrono::FixedOffset::east_opt(0).unwrap();
+    time::parse_datetime(s, utc).map(|_| ())
+}
+
+/// json 是 `{"type": "Id", "value": [1, 2]}`，也接受早先的 `{"Id": [1, 2]}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", remote = "Self")]
 pub enum ListValue {
     Id(Vec<u64>),
     Int(Vec<u64>),
     Num(Vec<f64>),
+    Decimal(Vec<Decimal>),
     Str(Vec<StrValue>),
     DateTime(Vec<StrValue>),
     UnixTiemstamp(Vec<u64>),
 }
 impl ListValue {
+    pub fn len(&self) -> usize {
+        match self {
+            ListValue::Id(v) | ListValue::Int(v) | ListValue::UnixTiemstamp(v) => v.len(),
+            ListValue::Num(v) => v.len(),
+            ListValue::Decimal(v) => v.len(),
+            ListValue::Str(v) | ListValue::DateTime(v) => v.len(),
+        }
+    }
+
+    /// 空列表：`In []` 为 FALSE，`NotIn []` 为 TRUE，字段为 NULL 时也一样
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn chekc_type(&self, ft: &FieldType) -> Result<(), DataAccessErr> {
         match (self, ft) {
-            (ListValue::Str(_), FieldType::Enum(_))
+            // 同 [`Value::chekc_type`]，枚举可以是整数或字符串
+            (ListValue::Int(_) | ListValue::Str(_), FieldType::Enum(_))
             | (ListValue::Id(_), FieldType::Id)
             | (ListValue::Int(_), FieldType::Int)
             | (ListValue::Num(_), FieldType::Num)
+            | (ListValue::Decimal(_), FieldType::Decimal { .. })
             | (ListValue::Str(_), FieldType::Str)
-            | (ListValue::DateTime(_), FieldType::DateTime)
-            | (ListValue::UnixTiemstamp(_), FieldType::UnixTiemstamp) => Ok(()),
+            | (ListValue::UnixTiemstamp(_), FieldType::DateTime | FieldType::UnixTiemstamp) => {
+                Ok(())
+            }
+            (ListValue::DateTime(v), FieldType::DateTime | FieldType::UnixTiemstamp) => {
+                v.iter().try_for_each(|s| check_datetime(&s.0))
+            }
             _ => Err(DataAccessErr::TypeErr),
         }
     }
 }
 
+impl Serialize for ListValue {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        ListValue::serialize(self, s)
+    }
+}
+impl<'de> Deserialize<'de> for ListValue {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum AnyListValue {
+            #[serde(with = "ListValue")]
+            Tagged(ListValue),
+            #[serde(with = "LegacyListValue")]
+            Legacy(ListValue),
+        }
+        match AnyListValue::deserialize(d)? {
+            AnyListValue::Tagged(v) | AnyListValue::Legacy(v) => Ok(v),
+        }
+    }
+}
+
+/// 早先 `ListValue` 的 json 格式
+#[derive(Deserialize)]
+#[serde(remote = "ListValue")]
+enum LegacyListValue {
+    Id(Vec<u64>),
+    Int(Vec<u64>),
+    Num(Vec<f64>),
+    Decimal(Vec<Decimal>),
+    Str(Vec<StrValue>),
+    DateTime(Vec<StrValue>),
+    UnixTiemstamp(Vec<u64>),
+}
+
 impl Display for ListValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         use ListValue::*;
@@ -254,11 +481,12 @@ impl Display for ListValue {
             Int(v) | Id(v) | UnixTiemstamp(v) => slice_join_bracket(f, v, ", "),
             Str(v) | DateTime(v) => slice_join_bracket(f, v, ", "),
             Num(v) => slice_join_bracket(f, v, ", "),
+            Decimal(v) => slice_join_bracket(f, v, ", "),
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FieldInfo {
     // pub list_key: String,
     pub name: String,
@@ -266,13 +494,23 @@ pub struct FieldInfo {
     pub nullable: bool,
     pub type_: FieldType,
     pub operation: String,
+    /// 是否可以排序，同时决定是否可以用于分页的游标
+    #[serde(default)]
+    pub sortable: bool,
+    /// 是否可以用于报表的聚合 (sum, avg, min, max, count)
+    #[serde(default)]
+    pub aggregatable: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FieldType {
     Str,
     Int,
     Num,
+    /// 定点小数，对应数据库的 `DECIMAL(p, scale)`，用于金额等需要精确比较的字段
+    Decimal {
+        scale: u32,
+    },
     DateTime,
     UnixTiemstamp,
     Id, // Id 类型应该只能进行 eq 操作
@@ -285,10 +523,49 @@ pub fn field_info_map(infos: Vec<FieldInfo>) -> HashMap<String, FieldInfo> {
     infos.into_iter().map(|f| (f.name.clone(), f)).collect()
 }
 
+/// 测试用的字段信息：可以为 NULL，不能排序和聚合，需要时用 `with_*` 修改
+#[cfg(test)]
+pub(crate) fn test_info(name: &str, type_: FieldType, operation: &str) -> FieldInfo {
+    FieldInfo {
+        name: name.into(),
+        text: "".into(),
+        nullable: true,
+        type_,
+        operation: operation.into(),
+        sortable: false,
+        aggregatable: false,
+    }
+}
+
+#[cfg(test)]
+impl FieldInfo {
+    pub(crate) fn with_text(mut self, text: &str) -> Self {
+        self.text = text.into();
+        self
+    }
+
+    pub(crate) fn with_nullable(mut self, nullable: bool) -> Self {
+        self.nullable = nullable;
+        self
+    }
+
+    pub(crate) fn with_sortable(mut self, sortable: bool) -> Self {
+        self.sortable = sortable;
+        self
+    }
+
+    pub(crate) fn with_aggregatable(mut self, aggregatable: bool) -> Self {
+        self.aggregatable = aggregatable;
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::Between as B;
     use super::FilterNode::{self, *};
-    use super::{Cmp::*, Eq::*, Field, Logical::*, StrValue, Value::*};
+    use super::{field_info_map, test_info, DataAccessErr, FieldType as FT, Nullable::*, Value};
+    use super::{Cmp::*, Eq::*, Field, Logical::*, Not::Not as NotOpe, StrValue, Value::*};
     // use super::{FieldInfo, In::*, FieldType as FT};
     #[test]
     fn serde_and_or() {
@@ -333,6 +610,19 @@ mod tests {
     //     }];
     // }
 
+    #[test]
+    fn display_empty_list() {
+        use super::{In as I, ListValue};
+        let root = Logical(
+            Or,
+            vec![
+                In(I::In, Field("id".into()), ListValue::Id(vec![])),
+                In(I::NotIn, Field("id".into()), ListValue::Id(vec![])),
+            ],
+        );
+        assert_eq!(root.to_string(), "(1 = 0 OR 1 = 1)");
+    }
+
     #[test]
     fn serde_eq_node() {
         let json = r#"["Eq", "name", { "type": "Str", "value": "AAAA" }]"#;
@@ -346,5 +636,216 @@ mod tests {
         panic!("if let else, {}", eq);
     }
 
-}
+    #[test]
+    fn not_node() {
+        #[rustfmt::skip]
+        let root = &Not(NotOpe, Box::new(Logical(And, vec![
+            Equal(Eq, Field("tag".to_string()), Str(StrValue("A".to_string()))),
+            Equal(Eq, Field("status".to_string()), Int(2)),
+        ])));
+        assert_eq!(root.to_string(), "NOT (tag = 'A' AND status = 2)");
+        let root = &Logical(
+            Or,
+            vec![
+                Not(NotOpe, Box::new(Equal(Eq, Field("id".to_string()), Int(1)))),
+                Not(
+                    NotOpe,
+                    Box::new(Nullable(IsNull, Field("name".to_string()))),
+                ),
+            ],
+        );
+        assert_eq!(root.to_string(), "(NOT (id = 1) OR NOT (name IS NULL))");
+
+        let json = r#"["Not",["Eq","id",{"type":"Int","value":1}]]"#;
+        let node: FilterNode = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&node).unwrap(), json);
+    }
+
+    #[test]
+    fn check_not() {
+        let age = test_info("age", FT::Int, " Equal  Cmp ").with_text("年龄");
+        let infos = field_info_map(vec![age.with_nullable(false)]);
+        let root = Not(NotOpe, Box::new(Cmp(Gt, Field("age".to_string()), Int(18))));
+        assert_eq!(root.check(&infos), Ok(()));
+        let root = Not(
+            NotOpe,
+            Box::new(Cmp(Gt, Field("age".to_string()), Num(18.0))),
+        );
+        assert_eq!(root.check(&infos), Err(DataAccessErr::TypeErr));
+        let root = Not(NotOpe, Box::new(Nullable(IsNull, Field("age".to_string()))));
+        assert_eq!(root.check(&infos), Err(DataAccessErr::InvalidOperation));
+        let root = Not(
+            NotOpe,
+            Box::new(Cmp(Gt, Field("name".to_string()), Int(18))),
+        );
+        assert_eq!(root.check(&infos), Err(DataAccessErr::UnknownField));
+    }
+
+    #[test]
+    fn between() {
+        let time = || Field("reg_time".to_string());
+        let lo = || DateTime(StrValue("2022-01-01".to_string()));
+        let hi = || DateTime(StrValue("2022-02-01".to_string()));
+        let root = Between(B::Between, time(), lo(), hi());
+        assert_eq!(
+            root.to_string(),
+            "reg_time BETWEEN '2022-01-01' AND '2022-02-01'"
+        );
+        let root = Between(B::GtEqLt, time(), lo(), hi());
+        let res = "(reg_time >= '2022-01-01' AND reg_time < '2022-02-01')";
+        assert_eq!(root.to_string(), res);
+
+        let json = r#"["GtEqLt","reg_time",{"type":"DateTime","value":"2022-01-01"},{"type":"DateTime","value":"2022-02-01"}]"#;
+        assert_eq!(serde_json::from_str::<FilterNode>(json).unwrap(), root);
+
+        let reg_time = test_info("reg_time", FT::DateTime, " Cmp ").with_text("注册时间");
+        let infos = field_info_map(vec![reg_time.with_nullable(false)]);
+        assert_eq!(root.check(&infos), Ok(()));
+        // 时间戳可以和时间字符串互相转换
+        let root = Between(B::Between, time(), lo(), UnixTiemstamp(1643673600));
+        assert_eq!(root.check(&infos), Ok(()));
+        let root = Between(B::Between, time(), lo(), Num(1643673600.0));
+        assert_eq!(root.check(&infos), Err(DataAccessErr::TypeErr));
+        let bad = DateTime(StrValue("2022/02/01".to_string()));
+        let root = Between(B::Between, time(), lo(), bad);
+        assert_eq!(root.check(&infos), Err(DataAccessErr::InvalidFormat));
+    }
+
+    #[test]
+    fn to_sql_text() {
+        use super::{Clock, EvalContext};
+        use super::{RelativeTime as Rt, TimeUnit};
+        use chrono::{FixedOffset, TimeZone, Utc};
+        let infos = field_info_map(vec![
+            test_info("reg_time", FT::DateTime, " Cmp "),
+            test_info("sub_time", FT::UnixTiemstamp, " Cmp "),
+        ]);
+        let clock = Clock {
+            now: Utc.with_ymd_and_hms(2022, 3, 15, 18, 30, 0).unwrap(),
+            tz: FixedOffset::east_opt(8 * 3600).unwrap(),
+        };
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 0,
+            clock: &clock,
+        };
+        let field = |name: &str| Field(name.to_string());
+        let lo = DateTime(StrValue("2022-01-01".to_string()));
+        let root = Between(
+            B::Between,
+            field("reg_time"),
+            lo.clone(),
+            UnixTiemstamp(1643673600),
+        );
+        let res = "reg_time BETWEEN '2022-01-01 00:00:00' AND '2022-02-01 08:00:00'";
+        assert_eq!(root.to_sql_text(&ctx).as_deref(), Ok(res));
+        let root = Cmp(Gt, field("sub_time"), lo);
+        assert_eq!(
+            root.to_sql_text(&ctx).as_deref(),
+            Ok("sub_time > 1640966400")
+        );
+        let root = Cmp(Gt, field("sub_time"), Num(1.0));
+        assert_eq!(root.to_sql_text(&ctx), Err(DataAccessErr::TypeErr));
+        let yesterday = RelativeTime(Rt {
+            offset: -1,
+            unit: TimeUnit::Day,
+            trunc: Some(TimeUnit::Day),
+        });
+        let root = Cmp(GtEq, field("reg_time"), yesterday);
+        let res = "reg_time >= '2022-03-15 00:00:00'";
+        assert_eq!(root.to_sql_text(&ctx).as_deref(), Ok(res));
+    }
+
+    #[test]
+    fn field_value() {
+        let field = |name: &str| Field(name.to_string());
+        let gt = Cmp(Gt, field("updated_at"), Value::Field(field("created_at")));
+        let json = r#"["Gt","updated_at",{"type":"Field","value":"created_at"}]"#;
+        assert_eq!(serde_json::to_string(&gt).unwrap(), json);
+        let root = Logical(
+            And,
+            vec![
+                gt,
+                Equal(Eq, field("owner_id"), Value::Field(field("assigned_to"))),
+            ],
+        );
+        assert_eq!(
+            root.to_string(),
+            "(updated_at > created_at AND owner_id = assigned_to)"
+        );
 
+        let info = |name: &str, type_| test_info(name, type_, " Equal  Cmp ");
+        let mut infos = field_info_map(vec![
+            info("updated_at", FT::DateTime),
+            info("created_at", FT::DateTime),
+            info("owner_id", FT::Id),
+            info("assigned_to", FT::Id),
+        ]);
+        assert_eq!(root.check(&infos), Ok(()));
+        infos.insert("created_at".into(), info("created_at", FT::UnixTiemstamp));
+        assert_eq!(root.check(&infos), Err(DataAccessErr::TypeErr));
+        infos.remove("created_at");
+        assert_eq!(root.check(&infos), Err(DataAccessErr::UnknownField));
+    }
+
+    #[test]
+    fn serde_in_node() {
+        let json = r#"["In","id",{"type":"Id","value":[1,2]}]"#;
+        let node: FilterNode = serde_json::from_str(json).unwrap();
+        assert_eq!(node.to_string(), "id IN(1, 2)");
+        assert_eq!(serde_json::to_string(&node).unwrap(), json);
+    }
+
+    #[test]
+    fn serde_decimal() {
+        let json = r#"["And",[["GtEq","amount",{"type":"Decimal","value":18}],
+            ["In","amount",{"type":"Decimal","value":["19.99","0.1"]}]]]"#;
+        let node: FilterNode = serde_json::from_str(json).unwrap();
+        assert_eq!(node.to_string(), "(amount >= 18 AND amount IN(19.99, 0.1))");
+        assert_eq!(
+            serde_json::to_string(&node).unwrap(),
+            r#"["And",[["GtEq","amount",{"type":"Decimal","value":"18"}],"#.to_string()
+                + r#"["In","amount",{"type":"Decimal","value":["19.99","0.1"]}]]]"#
+        );
+        let amount = test_info("amount", FT::Decimal { scale: 2 }, " Cmp In ");
+        let infos = field_info_map(vec![amount]);
+        assert_eq!(node.check(&infos), Ok(()));
+        let json = r#"["GtEq","amount",{"type":"Num","value":18.0}]"#;
+        let node: FilterNode = serde_json::from_str(json).unwrap();
+        assert_eq!(node.check(&infos), Err(DataAccessErr::TypeErr));
+        let json = r#"["GtEq","amount",{"type":"Decimal","value":"1,5"}]"#;
+        assert!(serde_json::from_str::<FilterNode>(json).is_err());
+        let json = r#"["GtEq","amount",{"type":"Decimal","value":0.1}]"#;
+        assert!(serde_json::from_str::<FilterNode>(json).is_err());
+    }
+
+    /// 单个元素的 `In` 化简为 `Eq` 后，枚举字段的值为 `Str`，检查时也要接受
+    #[test]
+    fn enum_str_value() {
+        let status = test_info("status", FT::Enum("status".into()), " Equal In ");
+        let infos = field_info_map(vec![status]);
+        let paid = || StrValue("paid".into());
+        let list = super::ListValue::Str(vec![paid()]);
+        let node = FilterNode::In(super::In::In, Field("status".into()), list);
+        let eq = node.clone().simplify();
+        assert_eq!(eq, Equal(Eq, Field("status".into()), Str(paid())));
+        assert_eq!(node.check(&infos), Ok(()));
+        assert_eq!(eq.check(&infos), Ok(()));
+        assert_eq!(
+            Value::Num(1.0).chekc_type(&FT::Enum("status".into())),
+            Err(DataAccessErr::TypeErr)
+        );
+
+        // 单个值和列表的规则一致：`status = 1` 可以，`status in (1, 2)` 也可以
+        let ints = super::ListValue::Int(vec![1, 2]);
+        assert_eq!(Int(1).chekc_type(&FT::Enum("status".into())), Ok(()));
+        assert_eq!(ints.chekc_type(&FT::Enum("status".into())), Ok(()));
+        let node = FilterNode::In(super::In::In, Field("status".into()), ints);
+        assert_eq!(node.check(&infos), Ok(()));
+        let nums = super::ListValue::Num(vec![1.0]);
+        assert_eq!(
+            nums.chekc_type(&FT::Enum("status".into())),
+            Err(DataAccessErr::TypeErr)
+        );
+    }
+}
@@ -0,0 +1,587 @@
+use super::{Cmp, Decimal, Eq, FilterNode, In, ListValue, Logical, Nullable, Value};
+use serde::Serialize;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum WarningKind {
+    /// 永远为 FALSE
+    Unsatisfiable,
+    /// 永远为 TRUE
+    AlwaysTrue,
+    /// 可以去掉，不影响结果
+    Redundant,
+}
+
+/// 分析出的问题，`Redundant` 的第一个条件是多余的，第二个是蕴含它的条件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub clauses: Vec<FilterNode>,
+}
+
+/// 可以比较大小的值，字符串的大小和数据库的排序规则有关，不参与分析
+#[derive(Debug, Clone, Copy)]
+enum Lit {
+    Int(i128),
+    Num(f64),
+    Dec(Decimal),
+}
+
+fn cmp(a: Lit, b: Lit) -> Ordering {
+    match (a, b) {
+        (Lit::Int(a), Lit::Int(b)) => a.cmp(&b),
+        (Lit::Num(a), Lit::Num(b)) => a.total_cmp(&b),
+        (Lit::Int(a), Lit::Num(b)) => (a as f64).total_cmp(&b),
+        (Lit::Num(a), Lit::Int(b)) => a.total_cmp(&(b as f64)),
+        (Lit::Dec(a), Lit::Dec(b)) => a.cmp(&b),
+        (Lit::Dec(a), b) => cmp(Lit::Num(a.to_f64()), b),
+        (a, Lit::Dec(b)) => cmp(a, Lit::Num(b.to_f64())),
+    }
+}
+
+fn lit_eq(a: &Lit, b: &Lit) -> bool {
+    cmp(*a, *b) == Ordering::Equal
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bound {
+    v: Lit,
+    incl: bool,
+}
+
+/// 一个条件允许的取值，不包括 NULL
+#[derive(Debug, Clone)]
+enum Set {
+    /// 上下界，`None` 为无穷；整数的开区间转为闭区间
+    Range(Option<Bound>, Option<Bound>),
+    In(Vec<Lit>),
+    NotIn(Vec<Lit>),
+}
+
+/// 时间戳可以和 `DateTime` 字段比较，字段的值不一定是整数秒，不能按整数处理
+fn lit(v: &Value) -> Option<Lit> {
+    match v {
+        Value::Id(v) | Value::Int(v) => Some(Lit::Int(*v as i128)),
+        Value::UnixTiemstamp(v) => Some(Lit::Num(*v as f64)),
+        Value::Num(v) if !v.is_nan() => Some(Lit::Num(*v)),
+        Value::Decimal(v) => Some(Lit::Dec(*v)),
+        _ => None,
+    }
+}
+
+fn lits(v: &ListValue) -> Option<Vec<Lit>> {
+    match v {
+        ListValue::Id(v) | ListValue::Int(v) => {
+            Some(v.iter().map(|v| Lit::Int(*v as i128)).collect())
+        }
+        ListValue::UnixTiemstamp(v) => Some(v.iter().map(|v| Lit::Num(*v as f64)).collect()),
+        ListValue::Num(v) => v.iter().map(|v| lit(&Value::Num(*v))).collect(),
+        ListValue::Decimal(v) => Some(v.iter().map(|v| Lit::Dec(*v)).collect()),
+        ListValue::Str(v) | ListValue::DateTime(v) if v.is_empty() => Some(vec![]),
+        ListValue::Str(_) | ListValue::DateTime(_) => None,
+    }
+}
+
+/// 整数 `> 5` 即 `>= 6`，方便判断相邻的区间
+fn bound(v: Lit, incl: bool, step: i128) -> Bound {
+    match v {
+        Lit::Int(i) if !incl => Bound {
+            v: Lit::Int(i + step),
+            incl: true,
+        },
+        v => Bound { v, incl },
+    }
+}
+
+/// 字段和值都是字面量的比较条件
+fn atom(node: &FilterNode) -> Option<(&str, Set)> {
+    let set = match node {
+        FilterNode::Equal(Eq::Eq, _, v) => Set::In(vec![lit(v)?]),
+        FilterNode::Equal(Eq::NotEq, _, v) => Set::NotIn(vec![lit(v)?]),
+        FilterNode::Cmp(ope, _, v) => {
+            let v = lit(v)?;
+            match ope {
+                Cmp::Gt => Set::Range(Some(bound(v, false, 1)), None),
+                Cmp::GtEq => Set::Range(Some(bound(v, true, 1)), None),
+                Cmp::Lt => Set::Range(None, Some(bound(v, false, -1))),
+                Cmp::LtEq => Set::Range(None, Some(bound(v, true, -1))),
+            }
+        }
+        FilterNode::Between(ope, _, lo, hi) => {
+            let (lo_ope, hi_ope) = ope.bounds();
+            Set::Range(
+                Some(bound(lit(lo)?, lo_ope == Cmp::GtEq, 1)),
+                Some(bound(lit(hi)?, hi_ope == Cmp::LtEq, -1)),
+            )
+        }
+        FilterNode::In(In::In, _, v) => Set::In(lits(v)?),
+        FilterNode::In(In::NotIn, _, v) => Set::NotIn(lits(v)?),
+        _ => return None,
+    };
+    Some((field(node)?, set))
+}
+
+fn field(node: &FilterNode) -> Option<&str> {
+    match node {
+        FilterNode::Nullable(_, f)
+        | FilterNode::Equal(_, f, _)
+        | FilterNode::Cmp(_, f, _)
+        | FilterNode::Between(_, f, _, _)
+        | FilterNode::In(_, f, _)
+        | FilterNode::Like(_, f, _) => Some(&f.0),
+        _ => None,
+    }
+}
+
+/// `a` 的下界不低于 `b`
+fn lo_ge(a: &Option<Bound>, b: &Option<Bound>) -> bool {
+    match (a, b) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(a), Some(b)) => match cmp(a.v, b.v) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => b.incl || !a.incl,
+        },
+    }
+}
+
+/// `a` 的上界不高于 `b`
+fn hi_le(a: &Option<Bound>, b: &Option<Bound>) -> bool {
+    match (a, b) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(a), Some(b)) => match cmp(a.v, b.v) {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            Ordering::Equal => b.incl || !a.incl,
+        },
+    }
+}
+
+fn in_range(x: Lit, lo: &Option<Bound>, hi: &Option<Bound>) -> bool {
+    let point = Some(Bound { v: x, incl: true });
+    lo_ge(&point, lo) && hi_le(&point, hi)
+}
+
+fn range_empty(lo: &Option<Bound>, hi: &Option<Bound>) -> bool {
+    match (lo, hi) {
+        (Some(lo), Some(hi)) => match cmp(lo.v, hi.v) {
+            Ordering::Greater => true,
+            Ordering::Equal => !(lo.incl && hi.incl),
+            Ordering::Less => false,
+        },
+        _ => false,
+    }
+}
+
+fn contains(set: &Set, x: Lit) -> bool {
+    match set {
+        Set::Range(lo, hi) => in_range(x, lo, hi),
+        Set::In(v) => v.iter().any(|y| lit_eq(&x, y)),
+        Set::NotIn(v) => !v.iter().any(|y| lit_eq(&x, y)),
+    }
+}
+
+/// `a` 蕴含 `b`，无法判断时为 false
+fn subset(a: &Set, b: &Set) -> bool {
+    match (a, b) {
+        (Set::In(v), b) => v.iter().all(|x| contains(b, *x)),
+        (Set::Range(lo, hi), _) if range_empty(lo, hi) => true,
+        (Set::Range(lo, hi), Set::Range(blo, bhi)) => lo_ge(lo, blo) && hi_le(hi, bhi),
+        (Set::Range(lo, hi), Set::NotIn(v)) => !v.iter().any(|x| in_range(*x, lo, hi)),
+        (Set::NotIn(a), Set::NotIn(b)) => b.iter().all(|x| a.iter().any(|y| lit_eq(x, y))),
+        _ => false,
+    }
+}
+
+/// 区间内所有的值，只用于很小的区间
+fn points(lo: &Option<Bound>, hi: &Option<Bound>, max: usize) -> Option<Vec<Lit>> {
+    match (lo, hi) {
+        (Some(lo), Some(hi)) => match (lo.v, hi.v) {
+            (Lit::Int(a), Lit::Int(b)) if b - a < max as i128 => {
+                Some((a..=b).map(Lit::Int).collect())
+            }
+            (a, _) if lo.incl && hi.incl && cmp(lo.v, hi.v) == Ordering::Equal => Some(vec![a]),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 同一个字段的条件 AND 后没有满足的值
+fn unsatisfiable(sets: &[&Set]) -> bool {
+    let (mut lo, mut hi) = (None, None);
+    let mut only: Option<Vec<Lit>> = None;
+    let mut not = vec![];
+    for set in sets {
+        match set {
+            Set::Range(l, h) => {
+                if !lo_ge(&lo, l) {
+                    lo = *l;
+                }
+                if !hi_le(&hi, h) {
+                    hi = *h;
+                }
+            }
+            Set::In(v) => {
+                only = Some(match only {
+                    None => v.clone(),
+                    Some(o) => o.into_iter().filter(|x| contains(set, *x)).collect(),
+                })
+            }
+            Set::NotIn(v) => not.extend(v.iter().copied()),
+        }
+    }
+    let ok = |x: &Lit| in_range(*x, &lo, &hi) && !not.iter().any(|y| lit_eq(x, y));
+    match only {
+        Some(v) => !v.iter().any(ok),
+        None if range_empty(&lo, &hi) => true,
+        None => points(&lo, &hi, not.len()).is_some_and(|v| !v.iter().any(ok)),
+    }
+}
+
+/// 同一个字段的条件 OR 后包含所有非 NULL 的值
+fn covers_all(sets: &[&Set]) -> bool {
+    let nots: Vec<_> = sets
+        .iter()
+        .filter_map(|s| match s {
+            Set::NotIn(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+    if let Some((first, rest)) = nots.split_first() {
+        let covered = |x: &Lit| {
+            rest.iter().any(|v| !v.iter().any(|y| lit_eq(x, y)))
+                || sets
+                    .iter()
+                    .any(|s| !matches!(s, Set::NotIn(_)) && contains(s, *x))
+        };
+        return first.iter().all(covered);
+    }
+    let mut ranges: Vec<_> = sets
+        .iter()
+        .flat_map(|s| match s {
+            Set::Range(lo, hi) => vec![(*lo, *hi)],
+            Set::In(v) => v
+                .iter()
+                .map(|x| {
+                    (
+                        Some(Bound { v: *x, incl: true }),
+                        Some(Bound { v: *x, incl: true }),
+                    )
+                })
+                .collect(),
+            Set::NotIn(_) => vec![],
+        })
+        .collect();
+    ranges.sort_by(|a, b| match (a.0, b.0) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(a), Some(b)) => cmp(a.v, b.v).then(b.incl.cmp(&a.incl)),
+    });
+    // 从负无穷开始，已经连续覆盖到的上界
+    let mut reach: Option<Bound> = None;
+    for (lo, hi) in ranges {
+        let connected = match (reach, lo) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(r), Some(lo)) => match (r.v, lo.v) {
+                (Lit::Int(r), Lit::Int(lo)) => lo <= r + 1,
+                _ => match cmp(lo.v, r.v) {
+                    Ordering::Less => true,
+                    Ordering::Equal => r.incl || lo.incl,
+                    Ordering::Greater => false,
+                },
+            },
+        };
+        if !connected {
+            return false;
+        }
+        let hi = match hi {
+            None => return true,
+            Some(hi) => hi,
+        };
+        reach = match reach {
+            Some(r) if hi_le(&Some(hi), &Some(r)) => Some(r),
+            _ => Some(hi),
+        };
+    }
+    false
+}
+
+/// 单个条件是否为常量：`In []` 为 FALSE，`NotIn []` 为 TRUE
+///
+/// 空的范围在字段为 NULL 时是 NULL，只是永远不为 TRUE，
+/// `null_as_false` 时才当作 FALSE，不能用于 Not 之下的条件
+pub(super) fn constant(node: &FilterNode, null_as_false: bool) -> Option<bool> {
+    let (_, set) = atom(node)?;
+    match set {
+        Set::NotIn(v) if v.is_empty() => Some(true),
+        Set::In(v) if v.is_empty() => Some(false),
+        set if null_as_false && unsatisfiable(&[&set]) => Some(false),
+        _ => None,
+    }
+}
+
+/// 分组中同一个字段的条件
+struct FieldClauses<'a> {
+    field: &'a str,
+    atoms: Vec<(&'a FilterNode, Set)>,
+    is_null: Option<&'a FilterNode>,
+    not_null: Option<&'a FilterNode>,
+}
+
+impl<'a> FieldClauses<'a> {
+    fn group(nodes: &'a [FilterNode]) -> Vec<FieldClauses<'a>> {
+        let mut res: Vec<FieldClauses> = vec![];
+        for node in nodes.iter().filter(|n| constant(n, true).is_none()) {
+            let name = match field(node) {
+                Some(name) => name,
+                None => continue,
+            };
+            let i = match res.iter().position(|f| f.field == name) {
+                Some(i) => i,
+                None => {
+                    res.push(FieldClauses {
+                        field: name,
+                        atoms: vec![],
+                        is_null: None,
+                        not_null: None,
+                    });
+                    res.len() - 1
+                }
+            };
+            let clauses = &mut res[i];
+            match (node, atom(node)) {
+                (FilterNode::Nullable(Nullable::IsNull, _), _) => clauses.is_null = Some(node),
+                (FilterNode::Nullable(Nullable::IsNotNull, _), _) => clauses.not_null = Some(node),
+                (_, Some((_, set))) => clauses.atoms.push((node, set)),
+                _ => {}
+            }
+        }
+        res
+    }
+
+    fn nodes(&self) -> Vec<FilterNode> {
+        let atoms = self.atoms.iter().map(|(n, _)| *n);
+        let nulls = self.is_null.iter().chain(self.not_null.iter()).copied();
+        atoms.chain(nulls).cloned().collect()
+    }
+
+    fn sets(&self) -> Vec<&Set> {
+        self.atoms.iter().map(|(_, s)| s).collect()
+    }
+
+    /// AND 后为 FALSE，比较条件隐含了不为 NULL
+    fn unsatisfiable(&self) -> bool {
+        match self.is_null {
+            Some(_) => !self.atoms.is_empty() || self.not_null.is_some(),
+            None => unsatisfiable(&self.sets()),
+        }
+    }
+
+    /// OR 后为 TRUE，必须有 `IS NULL` 才能覆盖 NULL
+    fn always_true(&self) -> bool {
+        self.is_null.is_some() && (self.not_null.is_some() || covers_all(&self.sets()))
+    }
+
+    /// AND 中被其它条件蕴含的条件，OR 中蕴含其它条件的条件
+    fn redundant(&self, ope: &Logical, out: &mut Vec<Warning>) {
+        let warn = |out: &mut Vec<Warning>, a: &FilterNode, b: &FilterNode| {
+            out.push(Warning {
+                kind: WarningKind::Redundant,
+                clauses: vec![a.clone(), b.clone()],
+            })
+        };
+        for (i, (node, set)) in self.atoms.iter().enumerate() {
+            let other = self.atoms.iter().enumerate().find(|(j, (_, other))| {
+                let (narrow, wide) = match ope {
+                    Logical::And => (other, set),
+                    Logical::Or => (set, other),
+                };
+                // 相同的条件只保留第一个
+                *j != i && subset(narrow, wide) && (j < &i || !subset(wide, narrow))
+            });
+            if let Some((_, (other, _))) = other {
+                warn(out, node, other);
+            }
+        }
+        if let (Logical::And, Some(not_null), Some((node, _))) =
+            (ope, self.not_null, self.atoms.first())
+        {
+            warn(out, not_null, node);
+        }
+    }
+}
+
+/// 分组中可以确定的常量：AND 中有字段矛盾为 FALSE，OR 中有字段覆盖所有值为 TRUE
+///
+/// 矛盾的条件同 [`constant`]，字段为 NULL 时可能是 NULL，`null_as_false` 时才化简
+pub(super) fn group_constant(
+    ope: &Logical,
+    nodes: &[FilterNode],
+    null_as_false: bool,
+) -> Option<bool> {
+    let fields = FieldClauses::group(nodes);
+    match ope {
+        Logical::And if !null_as_false => None,
+        Logical::And => fields.iter().any(|f| f.unsatisfiable()).then_some(false),
+        Logical::Or => fields.iter().any(|f| f.always_true()).then_some(true),
+    }
+}
+
+impl FilterNode {
+    /// 静态分析，找出永远为 FALSE、永远为 TRUE 和多余的条件
+    ///
+    /// 只比较同一个 AND/OR 分组中同一个字段和数值字面量的条件，
+    /// 需要时调用 [`FilterNode::simplify`] 把常量化简掉
+    pub fn analyze(&self) -> Vec<Warning> {
+        let mut out = vec![];
+        self.walk_analyze(&mut out);
+        out
+    }
+
+    fn walk_analyze(&self, out: &mut Vec<Warning>) {
+        match self {
+            FilterNode::Logical(ope, v) => {
+                for f in FieldClauses::group(v) {
+                    let kind = match ope {
+                        Logical::And if f.unsatisfiable() => WarningKind::Unsatisfiable,
+                        Logical::Or if f.always_true() => WarningKind::AlwaysTrue,
+                        _ => {
+                            f.redundant(ope, out);
+                            continue;
+                        }
+                    };
+                    out.push(Warning {
+                        kind,
+                        clauses: f.nodes(),
+                    });
+                }
+                v.iter().for_each(|n| n.walk_analyze(out));
+            }
+            FilterNode::Not(_, n) => n.walk_analyze(out),
+            node => {
+                let kind = match constant(node, true) {
+                    Some(true) => WarningKind::AlwaysTrue,
+                    Some(false) => WarningKind::Unsatisfiable,
+                    None => return,
+                };
+                out.push(Warning {
+                    kind,
+                    clauses: vec![node.clone()],
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_info;
+    use super::super::{field_info_map, FieldInfo, FieldType as FT, FilterNode, Logical};
+    use super::{Warning, WarningKind as W};
+    use std::collections::HashMap;
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let info = |name, type_| test_info(name, type_, " Equal Cmp In ");
+        field_info_map(vec![
+            info("age", FT::Int),
+            info("score", FT::Num),
+            info("status", FT::Str),
+            info("reg_time", FT::DateTime),
+        ])
+    }
+
+    fn parse(s: &str) -> FilterNode {
+        FilterNode::parse(s, &infos()).unwrap()
+    }
+
+    fn kinds(s: &str) -> Vec<W> {
+        parse(s).analyze().into_iter().map(|w| w.kind).collect()
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        assert_eq!(kinds("age > 30 and age < 20"), [W::Unsatisfiable]);
+        assert_eq!(kinds("age > 5 and age < 6"), [W::Unsatisfiable]);
+        assert_eq!(kinds("score > 5 and score < 6"), []);
+        // DateTime 字段有小数秒，时间戳不能按整数处理
+        assert_eq!(kinds("reg_time > 5 and reg_time < 6"), []);
+        assert_eq!(kinds("reg_time > 6 and reg_time < 5"), [W::Unsatisfiable]);
+        assert_eq!(kinds("age in (1, 2) and age > 2"), [W::Unsatisfiable]);
+        assert_eq!(
+            kinds("age between [1, 2] and age <> 1 and age <> 2"),
+            [W::Unsatisfiable]
+        );
+        assert_eq!(kinds("age is null and age = 1"), [W::Unsatisfiable]);
+        assert_eq!(kinds("status in () or age = 1"), [W::Unsatisfiable]);
+        assert_eq!(
+            parse("age > 30 and (age < 20 and status = 'a')").analyze(),
+            []
+        );
+    }
+
+    #[test]
+    fn always_true() {
+        assert_eq!(kinds("status not in ()"), [W::AlwaysTrue]);
+        assert_eq!(kinds("age is null or age is not null"), [W::AlwaysTrue]);
+        // NULL 不满足任何比较条件
+        assert_eq!(kinds("age > 5 or age <= 5"), []);
+        assert_eq!(
+            kinds("age > 5 or age < 5 or age = 5 or age is null"),
+            [W::AlwaysTrue]
+        );
+        assert_eq!(
+            kinds("age <> 5 or age in (5, 6) or age is null"),
+            [W::AlwaysTrue]
+        );
+        assert_eq!(kinds("score < 5 or score > 5 or score is null"), []);
+    }
+
+    #[test]
+    fn redundant() {
+        let w = parse("age > 5 and age > 3 and age is not null").analyze();
+        #[rustfmt::skip]
+        assert_eq!(w, [
+            Warning { kind: W::Redundant, clauses: vec![parse("age > 3"), parse("age > 5")] },
+            Warning { kind: W::Redundant, clauses: vec![parse("age is not null"), parse("age > 5")] },
+        ]);
+        let w = parse("age in (1, 2) or age = 1").analyze();
+        #[rustfmt::skip]
+        assert_eq!(w, [
+            Warning { kind: W::Redundant, clauses: vec![parse("age = 1"), parse("age in (1, 2)")] },
+        ]);
+        assert_eq!(kinds("age = 1 and age = 1"), [W::Redundant]);
+    }
+
+    #[test]
+    fn simplify() {
+        let f = FilterNode::Logical(Logical::Or, vec![]);
+        let t = FilterNode::Logical(Logical::And, vec![]);
+        assert_eq!(
+            parse("age > 30 and age < 20 and status = 'a'").simplify(),
+            f
+        );
+        assert_eq!(parse("status = 'a' and status in ()").simplify(), f);
+        assert_eq!(parse("status = 'a' or status not in ()").simplify(), t);
+        assert_eq!(
+            parse("status = 'a' or (age > 30 and age < 20)").simplify(),
+            parse("status = 'a'")
+        );
+        // 字段为 NULL 时矛盾的条件是 NULL，取反后仍是 NULL，Not 之下不能化简
+        for s in [
+            "not (age > 30 and age < 20)",
+            "not (age is null and age = 1)",
+            "not age between [30, 20]",
+        ] {
+            assert_eq!(parse(s).simplify(), parse(s), "{}", s);
+        }
+        let s = "reg_time > 5 and reg_time < 6";
+        assert_eq!(parse(s).simplify(), parse(s));
+        assert_eq!(parse("not not (age > 30 and age < 20)").simplify(), f);
+        assert_eq!(parse("not (status in () and age > 1)").simplify(), t);
+    }
+}
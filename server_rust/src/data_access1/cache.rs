@@ -0,0 +1,259 @@
+use super::sql::{array_bind, like_pattern, list_binds, value_bind, Bind, Dialect, Sql};
+use super::{
+    DataAccessErr, EvalContext, Field, FilterLimits, FilterNode, ListValue, Logical, Value,
+};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+impl FilterNode {
+    /// 条件的形状：值替换为 `?`，只保留结构、字段和运算符
+    ///
+    /// 形状相同的条件编译出的 sql 文本相同，只有参数不同；
+    /// `In` 列表的长度会影响占位符的个数，所以也是形状的一部分
+    pub fn shape(&self) -> String {
+        let mut s = String::new();
+        self.write_shape(&mut s, &|_| false);
+        s
+    }
+
+    /// 写入 `String` 不会失败；`array` 为 true 的列表绑定为一个数组参数，
+    /// 和长度无关，写成 `[*]`
+    fn write_shape(&self, s: &mut String, array: &impl Fn(&ListValue) -> bool) {
+        let value = |s: &mut String, v: &Value| match v {
+            Value::Field(f) => write!(s, "@{:?}", f.0),
+            _ => write!(s, "?"),
+        };
+        let _ = match self {
+            FilterNode::Logical(ope, v) => {
+                s.push_str(match ope {
+                    Logical::And => "and(",
+                    Logical::Or => "or(",
+                });
+                for (i, n) in v.iter().enumerate() {
+                    if i > 0 {
+                        s.push(',');
+                    }
+                    n.write_shape(s, array);
+                }
+                write!(s, ")")
+            }
+            FilterNode::Not(_, n) => {
+                s.push_str("not(");
+                n.write_shape(s, array);
+                write!(s, ")")
+            }
+            FilterNode::Nullable(ope, field) => write!(s, "{:?} {}", field.0, ope),
+            FilterNode::Equal(ope, field, v) => {
+                let _ = write!(s, "{:?} {} ", field.0, ope);
+                value(s, v)
+            }
+            FilterNode::Cmp(ope, field, v) => {
+                let _ = write!(s, "{:?} {} ", field.0, ope);
+                value(s, v)
+            }
+            FilterNode::Between(ope, field, lo, hi) => {
+                let _ = write!(s, "{:?} {:?} ", field.0, ope);
+                let _ = value(s, lo);
+                s.push(' ');
+                value(s, hi)
+            }
+            FilterNode::In(ope, field, v) if !v.is_empty() && array(v) => {
+                write!(s, "{:?} {} [*]", field.0, ope)
+            }
+            FilterNode::In(ope, field, v) => write!(s, "{:?} {} [{}]", field.0, ope, v.len()),
+            FilterNode::Like(_, field, _) => write!(s, "{:?} LIKE ?", field.0),
+        };
+    }
+}
+
+/// 按 [`Sql::node`] 的顺序取出参数，类型检查和数组参数也和它一致
+fn binds(node: &FilterNode, ctx: &EvalContext, sql: &mut Sql) -> Result<(), DataAccessErr> {
+    let ft = |field: &Field| {
+        let info = ctx.infos.get(&field.0).ok_or(DataAccessErr::UnknownField)?;
+        Ok::<_, DataAccessErr>(&info.type_)
+    };
+    let value = |sql: &mut Sql, v, field| {
+        if let Some(b) = value_bind(v, ft(field)?, ctx)? {
+            sql.binds.push(b);
+        }
+        Ok::<_, DataAccessErr>(())
+    };
+    match node {
+        FilterNode::Logical(_, v) => v.iter().try_for_each(|n| binds(n, ctx, sql))?,
+        FilterNode::Not(_, n) => binds(n, ctx, sql)?,
+        FilterNode::Nullable(..) => {}
+        FilterNode::Equal(_, field, v) | FilterNode::Cmp(_, field, v) => value(sql, v, field)?,
+        FilterNode::Between(_, field, lo, hi) => {
+            value(sql, lo, field)?;
+            value(sql, hi, field)?;
+        }
+        FilterNode::In(_, _, v) if v.is_empty() => {}
+        FilterNode::In(_, field, v) if sql.use_array(v) => {
+            v.chekc_type(ft(field)?)?;
+            let b = array_bind(v, ft(field)?, ctx)?;
+            sql.binds.push(b);
+        }
+        FilterNode::In(_, field, v) => {
+            v.chekc_type(ft(field)?)?;
+            list_binds(v, ft(field)?, ctx, |b| sql.binds.push(b))?;
+        }
+        FilterNode::Like(ope, _, v) => sql.binds.push(Bind::Str(like_pattern(ope, v))),
+    }
+    Ok(())
+}
+
+/// 按形状和方言缓存编译好的 sql 文本，每次请求只重新绑定参数
+///
+/// 文本相同也方便数据库复用预编译语句。缓存满时整体清空
+pub struct SqlCache {
+    capacity: usize,
+    map: Mutex<HashMap<(Dialect, String), Arc<str>>>,
+    /// 同 [`Sql::array_min_len`]，绑定为数组的列表长度不同也共用一条缓存
+    pub array_min_len: Option<usize>,
+}
+
+impl SqlCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            map: Mutex::new(HashMap::new()),
+            array_min_len: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 同 [`FilterNode::to_sql`]，形状相同时复用缓存的文本
+    ///
+    /// 只用于单独的 WHERE 条件，postgres 的占位符从 `$1` 开始
+    pub fn to_sql(
+        &self,
+        node: &FilterNode,
+        dialect: Dialect,
+        ctx: &EvalContext,
+        limits: &FilterLimits,
+    ) -> Result<Sql, DataAccessErr> {
+        node.check_limits(limits)?;
+        node.check(ctx.infos)?;
+        let mut sql = Sql::new(dialect);
+        sql.array_min_len = self.array_min_len;
+        let mut shape = String::new();
+        node.write_shape(&mut shape, &|v| sql.use_array(v));
+        let key = (dialect, shape);
+        let text = self
+            .map
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+            .cloned();
+        match text {
+            Some(text) => {
+                binds(node, ctx, &mut sql)?;
+                sql.push(&text);
+            }
+            None => {
+                sql.node(node, ctx)?;
+                let mut map = self.map.lock().unwrap_or_else(|e| e.into_inner());
+                if map.len() >= self.capacity {
+                    map.clear();
+                }
+                map.insert(key, sql.sql.as_str().into());
+            }
+        }
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, test_info, Clock, EvalContext, FieldInfo, FieldType as FT};
+    use super::super::{Dialect, FilterLimits, FilterNode, Sql};
+    use super::SqlCache;
+    use chrono::{FixedOffset, TimeZone, Utc};
+    use std::collections::HashMap;
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let info = |name, type_| test_info(name, type_, " Equal Cmp In Like ");
+        field_info_map(vec![
+            info("age", FT::Int),
+            info("min_age", FT::Int),
+            info("name", FT::Str),
+            info("reg_time", FT::DateTime),
+        ])
+    }
+
+    #[test]
+    fn shape() {
+        let infos = infos();
+        let shape = |s| FilterNode::parse(s, &infos).unwrap().shape();
+        assert_eq!(
+            shape("age >= 18 and name like 'a%'"),
+            shape("age >= 30 and name like '%b'")
+        );
+        assert_eq!(
+            shape("age >= 18 and name like 'a%'"),
+            r#"and("age" >= ?,"name" LIKE ?)"#
+        );
+        assert_ne!(shape("age in (1, 2)"), shape("age in (1, 2, 3)"));
+        assert_ne!(shape("age > 1"), shape("age > min_age"));
+        assert_ne!(shape("age > 1"), shape("age >= 1"));
+    }
+
+    #[test]
+    fn rebind() {
+        let infos = infos();
+        let clock = Clock {
+            now: Utc.with_ymd_and_hms(2022, 3, 15, 18, 30, 0).unwrap(),
+            tz: FixedOffset::east_opt(8 * 3600).unwrap(),
+        };
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 7,
+            clock: &clock,
+        };
+        let limits = FilterLimits::default();
+        let cache = SqlCache::new(10);
+        let filters = [
+            "age >= 18 and (name like '1%' or age in (1, 2)) and reg_time < current_date",
+            "age >= 30 and (name like '%2_%' or age in (3, 4)) and reg_time < '2022-01-01 00:00:00'",
+            "age > min_age and name in ()",
+            "age > min_age and name in ()",
+        ];
+        for dialect in [Dialect::Postgres, Dialect::MySql] {
+            for src in filters {
+                let node = FilterNode::parse(src, &infos).unwrap();
+                let cached = cache.to_sql(&node, dialect, &ctx, &limits).unwrap();
+                assert_eq!(cached, node.to_sql(dialect, &ctx, &limits).unwrap());
+            }
+        }
+        assert_eq!(cache.len(), 4);
+
+        // 绑定为数组的列表长度不同也共用一条缓存，参数和不用缓存时一致
+        let mut cache = SqlCache::new(10);
+        cache.array_min_len = Some(3);
+        for src in ["age in (1, 2, 3)", "age in (4, 5, 6, 7)", "age in (1, 2)"] {
+            let node = FilterNode::parse(src, &infos).unwrap();
+            let cached = cache
+                .to_sql(&node, Dialect::Postgres, &ctx, &limits)
+                .unwrap();
+            let mut sql = Sql::new(Dialect::Postgres);
+            sql.array_min_len = Some(3);
+            node.write_sql(&mut sql, &ctx, &limits).unwrap();
+            assert_eq!(cached, sql);
+        }
+        assert_eq!(cache.len(), 2);
+
+        let node = FilterNode::parse("age > 1", &infos).unwrap();
+        let small = SqlCache::new(1);
+        small.to_sql(&node, Dialect::Sqlite, &ctx, &limits).unwrap();
+        small.to_sql(&node, Dialect::MySql, &ctx, &limits).unwrap();
+        assert_eq!(small.len(), 1);
+    }
+}
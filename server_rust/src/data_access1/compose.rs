@@ -0,0 +1,283 @@
+use super::analyze::{constant, group_constant};
+use super::{Cmp, Eq, FilterNode, In, ListValue, Logical, Not, Nullable, Value};
+
+impl FilterNode {
+    /// `self AND rhs`，结果未化简，需要时调用 [`FilterNode::simplify`]
+    pub fn and(self, rhs: FilterNode) -> FilterNode {
+        FilterNode::Logical(Logical::And, vec![self, rhs])
+    }
+
+    /// `self OR rhs`，结果未化简，需要时调用 [`FilterNode::simplify`]
+    pub fn or(self, rhs: FilterNode) -> FilterNode {
+        FilterNode::Logical(Logical::Or, vec![self, rhs])
+    }
+
+    /// 取反，And/Or 按德摩根定律展开，比较运算取相反的运算符
+    ///
+    /// Like 没有对应的相反运算符，包一层 Not
+    pub fn negate(self) -> FilterNode {
+        match self {
+            FilterNode::Logical(ope, v) => {
+                let ope = match ope {
+                    Logical::And => Logical::Or,
+                    Logical::Or => Logical::And,
+                };
+                FilterNode::Logical(ope, v.into_iter().map(FilterNode::negate).collect())
+            }
+            FilterNode::Not(_, n) => *n,
+            FilterNode::Nullable(ope, field) => match ope {
+                Nullable::IsNull => FilterNode::Nullable(Nullable::IsNotNull, field),
+                Nullable::IsNotNull => FilterNode::Nullable(Nullable::IsNull, field),
+            },
+            FilterNode::Equal(ope, field, v) => match ope {
+                Eq::Eq => FilterNode::Equal(Eq::NotEq, field, v),
+                Eq::NotEq => FilterNode::Equal(Eq::Eq, field, v),
+            },
+            FilterNode::Cmp(ope, field, v) => FilterNode::Cmp(ope.negate(), field, v),
+            FilterNode::Between(ope, field, lo, hi) => {
+                let (lo_ope, hi_ope) = ope.bounds();
+                let v = vec![
+                    FilterNode::Cmp(lo_ope.negate(), field.clone(), lo),
+                    FilterNode::Cmp(hi_ope.negate(), field, hi),
+                ];
+                FilterNode::Logical(Logical::Or, v)
+            }
+            FilterNode::In(ope, field, v) => match ope {
+                In::In => FilterNode::In(In::NotIn, field, v),
+                In::NotIn => FilterNode::In(In::In, field, v),
+            },
+            node @ FilterNode::Like(..) => !node,
+        }
+    }
+
+    /// 把 Not 节点下推到叶子（德摩根形式），供不支持 NOT 的后端使用
+    ///
+    /// 结果中 Not 只会直接包住 Like 节点
+    pub fn push_not(self) -> FilterNode {
+        match self {
+            FilterNode::Logical(ope, v) => {
+                FilterNode::Logical(ope, v.into_iter().map(FilterNode::push_not).collect())
+            }
+            FilterNode::Not(_, n) => n.push_not().negate(),
+            node => node,
+        }
+    }
+
+    /// 化简：
+    /// - 展开嵌套的同类 Logical 节点，只剩一个子节点时直接返回该子节点
+    /// - 常量 TRUE 为 `And []`，FALSE 为 `Or []`；AND 中的 FALSE、OR 中的 TRUE
+    ///   决定整个分组，另一个常量直接去掉
+    /// - [`FilterNode::analyze`] 能确定的矛盾和恒真条件化简为常量；矛盾的条件
+    ///   在字段为 NULL 时是 NULL，取反后仍是 NULL，所以在 Not 之下保留原样
+    /// - 只有一个元素的 In/NotIn 转为 Eq/NotEq
+    /// - 去掉双重否定
+    /// - 去掉重复的条件
+    pub fn simplify(self) -> FilterNode {
+        self.simplify_in(false)
+    }
+
+    /// `negated` 为在奇数个 Not 之下
+    fn simplify_in(self, negated: bool) -> FilterNode {
+        match self {
+            FilterNode::Logical(ope, v) => {
+                let mut nodes: Vec<FilterNode> = Vec::with_capacity(v.len());
+                for node in v.into_iter().map(|n| n.simplify_in(negated)) {
+                    match node {
+                        FilterNode::Logical(sub_ope, sub) if sub_ope == ope => {
+                            sub.into_iter().for_each(|n| push_unique(&mut nodes, n))
+                        }
+                        FilterNode::Logical(sub_ope, sub) if sub.is_empty() => {
+                            return FilterNode::Logical(sub_ope, sub)
+                        }
+                        node => push_unique(&mut nodes, node),
+                    }
+                }
+                if let Some(b) = group_constant(&ope, &nodes, !negated) {
+                    return constant_node(b);
+                }
+                match nodes.len() {
+                    1 => nodes.pop().unwrap(),
+                    _ => FilterNode::Logical(ope, nodes),
+                }
+            }
+            FilterNode::Not(ope, n) => match n.simplify_in(!negated) {
+                FilterNode::Not(_, n) => *n,
+                FilterNode::Logical(Logical::And, v) if v.is_empty() => constant_node(false),
+                FilterNode::Logical(Logical::Or, v) if v.is_empty() => constant_node(true),
+                n => FilterNode::Not(ope, Box::new(n)),
+            },
+            node => match constant(&node, !negated) {
+                Some(b) => constant_node(b),
+                None => match node {
+                    FilterNode::In(ope, field, v) => match single_value(v) {
+                        Ok(value) => match ope {
+                            In::In => FilterNode::Equal(Eq::Eq, field, value),
+                            In::NotIn => FilterNode::Equal(Eq::NotEq, field, value),
+                        },
+                        Err(v) => FilterNode::In(ope, field, v),
+                    },
+                    node => node,
+                },
+            },
+        }
+    }
+}
+
+impl Cmp {
+    fn negate(self) -> Cmp {
+        match self {
+            Cmp::Gt => Cmp::LtEq,
+            Cmp::GtEq => Cmp::Lt,
+            Cmp::Lt => Cmp::GtEq,
+            Cmp::LtEq => Cmp::Gt,
+        }
+    }
+}
+
+/// `!node` 即 `NOT node`，不展开，需要时调用 [`FilterNode::push_not`]
+impl std::ops::Not for FilterNode {
+    type Output = FilterNode;
+    fn not(self) -> FilterNode {
+        FilterNode::Not(Not::Not, Box::new(self))
+    }
+}
+
+/// TRUE 为 `And []`，FALSE 为 `Or []`
+fn constant_node(b: bool) -> FilterNode {
+    match b {
+        true => FilterNode::Logical(Logical::And, vec![]),
+        false => FilterNode::Logical(Logical::Or, vec![]),
+    }
+}
+
+fn push_unique(nodes: &mut Vec<FilterNode>, node: FilterNode) {
+    if !nodes.contains(&node) {
+        nodes.push(node)
+    }
+}
+
+/// 只有一个元素的列表转为对应的 [`Value`]，否则原样返回
+fn single_value(list: ListValue) -> Result<Value, ListValue> {
+    fn one<T>(mut v: Vec<T>) -> Result<T, Vec<T>> {
+        match v.len() {
+            1 => Ok(v.pop().unwrap()),
+            _ => Err(v),
+        }
+    }
+    match list {
+        ListValue::Id(v) => one(v).map(Value::Id).map_err(ListValue::Id),
+        ListValue::Int(v) => one(v).map(Value::Int).map_err(ListValue::Int),
+        ListValue::Num(v) => one(v).map(Value::Num).map_err(ListValue::Num),
+        ListValue::Decimal(v) => one(v).map(Value::Decimal).map_err(ListValue::Decimal),
+        ListValue::Str(v) => one(v).map(Value::Str).map_err(ListValue::Str),
+        ListValue::DateTime(v) => one(v).map(Value::DateTime).map_err(ListValue::DateTime),
+        ListValue::UnixTiemstamp(v) => one(v)
+            .map(Value::UnixTiemstamp)
+            .map_err(ListValue::UnixTiemstamp),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::FilterNode::{self, *};
+    use super::super::{Cmp::*, Eq::*, Field, In as I, ListValue, Logical::*, Nullable::*, Value};
+
+    fn field(name: &str) -> Field {
+        Field(name.to_string())
+    }
+
+    #[test]
+    fn and_or_flatten() {
+        let a = Equal(Eq, field("id"), Value::Int(1));
+        let b = Cmp(Gt, field("age"), Value::Int(18));
+        let c = Nullable(IsNull, field("name"));
+        let root = a.clone().and(b.clone()).and(c.clone()).simplify();
+        assert_eq!(root, Logical(And, vec![a, b, c]));
+        assert_eq!(root.to_string(), "(id = 1 AND age > 18 AND (name IS NULL))");
+    }
+
+    #[test]
+    fn simplify_drop_empty_and_dedup() {
+        let a = Equal(Eq, field("id"), Value::Int(1));
+        #[rustfmt::skip]
+        let root = Logical(Or, vec![
+            Logical(Or, vec![]),
+            a.clone(),
+            Logical(Or, vec![a.clone(), Logical(Or, vec![])]),
+        ]);
+        assert_eq!(root.simplify(), a);
+        // And [] 是 TRUE，OR 中有 TRUE 时整个分组为 TRUE
+        let root = Logical(Or, vec![Logical(And, vec![]), a.clone()]);
+        assert_eq!(root.simplify(), Logical(And, vec![]));
+    }
+
+    #[test]
+    fn simplify_single_in() {
+        let root = In(I::NotIn, field("id"), ListValue::Id(vec![3])).simplify();
+        assert_eq!(root, Equal(NotEq, field("id"), Value::Id(3)));
+        let root = In(I::In, field("id"), ListValue::Id(vec![3, 4]));
+        assert_eq!(root.clone().simplify(), root);
+    }
+
+    #[test]
+    fn negate() {
+        #[rustfmt::skip]
+        let root = Logical(And, vec![
+            Cmp(Gt, field("age"), Value::Int(18)),
+            Logical(Or, vec![
+                Nullable(IsNull, field("name")),
+                In(I::In, field("id"), ListValue::Id(vec![1, 2])),
+            ]),
+        ]);
+        #[rustfmt::skip]
+        let res = Logical(Or, vec![
+            Cmp(LtEq, field("age"), Value::Int(18)),
+            Logical(And, vec![
+                Nullable(IsNotNull, field("name")),
+                In(I::NotIn, field("id"), ListValue::Id(vec![1, 2])),
+            ]),
+        ]);
+        assert_eq!(root.negate(), res);
+    }
+
+    #[test]
+    fn negate_like() {
+        let like: FilterNode = serde_json::from_str(r#"["Contains", "name", "a"]"#).unwrap();
+        assert_eq!(like.clone().negate(), !like.clone());
+        assert_eq!((!like.clone()).negate(), like);
+    }
+
+    #[test]
+    fn push_not() {
+        let like: FilterNode = serde_json::from_str(r#"["Contains", "name", "a"]"#).unwrap();
+        let eq = Equal(Eq, field("id"), Value::Int(1));
+        let root = (!eq.clone().and(!like.clone())).push_not();
+        assert_eq!(
+            root,
+            Logical(Or, vec![Equal(NotEq, field("id"), Value::Int(1)), like])
+        );
+    }
+
+    #[test]
+    fn negate_between() {
+        let (lo, hi) = (Value::Int(18), Value::Int(30));
+        let root = Between(
+            super::super::Between::GtEqLt,
+            field("age"),
+            lo.clone(),
+            hi.clone(),
+        );
+        #[rustfmt::skip]
+        let res = Logical(Or, vec![
+            Cmp(Lt, field("age"), lo),
+            Cmp(GtEq, field("age"), hi),
+        ]);
+        assert_eq!(root.negate(), res);
+    }
+
+    #[test]
+    fn simplify_double_not() {
+        let eq = Equal(Eq, field("id"), Value::Int(1));
+        assert_eq!((!!eq.clone()).simplify(), eq);
+    }
+}
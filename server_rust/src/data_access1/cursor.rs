@@ -0,0 +1,266 @@
+use super::eval::Record;
+use super::query::Page;
+use super::sort::{Direction, SortSpec};
+use super::{
+    Cmp, DataAccessErr, Decimal, Eq, Field, FieldInfo, FieldType, FilterNode, Logical, StrValue,
+    Value,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as B64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 游标分页的位置：上一页最后一行的排序字段值，和排序字段一一对应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub after: Vec<Value>,
+}
+
+/// `(a > x) OR (a = x AND b > y) ...`，降序的字段用 `<`
+pub(super) fn keyset(sort: &SortSpec, after: &[Value]) -> FilterNode {
+    let key = |i: usize| Field(sort.0[i].field.clone());
+    let branches = (0..sort.0.len()).map(|i| {
+        let mut v: Vec<_> = (0..i)
+            .map(|j| FilterNode::Equal(Eq::Eq, key(j), after[j].clone()))
+            .collect();
+        let cmp = match sort.0[i].direction {
+            Direction::Asc => Cmp::Gt,
+            Direction::Desc => Cmp::Lt,
+        };
+        v.push(FilterNode::Cmp(cmp, key(i), after[i].clone()));
+        FilterNode::Logical(Logical::And, v)
+    });
+    FilterNode::Logical(Logical::Or, branches.collect()).simplify()
+}
+
+/// 单元格的 json 转为字段类型对应的值，NULL 无法作为游标
+fn cell_value(v: Option<&Json>, ft: &FieldType) -> Result<Value, DataAccessErr> {
+    use FieldType as FT;
+    let uint = |v: &Json| v.as_u64().ok_or(DataAccessErr::TypeErr);
+    let str = |v: &Json| match v {
+        Json::String(s) => Ok(StrValue(s.clone())),
+        _ => Err(DataAccessErr::TypeErr),
+    };
+    let v = match v {
+        None | Some(Json::Null) => return Err(DataAccessErr::InvalidOperation),
+        Some(v) => v,
+    };
+    Ok(match ft {
+        FT::Id => Value::Id(uint(v)?),
+        FT::Int => Value::Int(uint(v)?),
+        FT::UnixTiemstamp => Value::UnixTiemstamp(uint(v)?),
+        FT::Num => Value::Num(v.as_f64().ok_or(DataAccessErr::TypeErr)?),
+        FT::Decimal { .. } => Value::Decimal(Decimal::from_json(v).ok_or(DataAccessErr::TypeErr)?),
+        FT::Str => Value::Str(str(v)?),
+        FT::DateTime => Value::DateTime(str(v)?),
+        FT::Enum(_) if v.is_u64() => Value::Int(uint(v)?),
+        FT::Enum(_) => Value::Str(str(v)?),
+    })
+}
+
+impl Cursor {
+    /// 从上一页的最后一行取出排序字段的值
+    pub fn from_row(
+        sort: &SortSpec,
+        row: &impl Record,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<Cursor, DataAccessErr> {
+        sort.check(infos)?;
+        let after = sort.0.iter().map(|item| {
+            let info = infos.get(&item.field).ok_or(DataAccessErr::UnknownField)?;
+            cell_value(row.get(&item.field), &info.type_)
+        });
+        Ok(Cursor {
+            after: after.collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// 游标之后的行，和原来的过滤条件 AND 在一起使用
+    pub fn filter(&self, sort: &SortSpec) -> Result<FilterNode, DataAccessErr> {
+        if sort.is_empty() || self.after.len() != sort.0.len() {
+            return Err(DataAccessErr::InvalidFormat);
+        }
+        Ok(keyset(sort, &self.after))
+    }
+
+    pub fn page(self, limit: u64) -> Page {
+        Page::Keyset {
+            limit,
+            after: self.after,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Payload {
+    /// 过滤条件和排序的摘要，游标只能用于生成它的查询
+    query: String,
+    after: Vec<Value>,
+}
+
+fn query_digest(filter: Option<&FilterNode>, sort: &SortSpec) -> String {
+    let json = serde_json::to_vec(&(filter, sort)).unwrap();
+    let hash = Sha256::digest(json);
+    B64.encode(&hash[..16])
+}
+
+/// 游标的签名密钥，生成的 token 不透明且不可篡改
+pub struct CursorKey {
+    key: Vec<u8>,
+}
+
+impl CursorKey {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            key: secret.to_vec(),
+        }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key).expect("hmac 接受任意长度的密钥")
+    }
+
+    /// `base64(payload).base64(hmac)`
+    pub fn encode(&self, cursor: &Cursor, filter: Option<&FilterNode>, sort: &SortSpec) -> String {
+        let payload = Payload {
+            query: query_digest(filter, sort),
+            after: cursor.after.clone(),
+        };
+        let payload = serde_json::to_vec(&payload).unwrap();
+        let mut mac = self.mac();
+        mac.update(&payload);
+        let sig = mac.finalize().into_bytes();
+        format!("{}.{}", B64.encode(payload), B64.encode(sig))
+    }
+
+    /// 校验签名，并检查游标是由同样的过滤条件和排序生成的，否则为 `InvalidFormat`
+    pub fn decode(
+        &self,
+        token: &str,
+        filter: Option<&FilterNode>,
+        sort: &SortSpec,
+    ) -> Result<Cursor, DataAccessErr> {
+        let (payload, sig) = token.split_once('.').ok_or(DataAccessErr::InvalidFormat)?;
+        let payload = B64
+            .decode(payload)
+            .map_err(|_| DataAccessErr::InvalidFormat)?;
+        let sig = B64.decode(sig).map_err(|_| DataAccessErr::InvalidFormat)?;
+        let mut mac = self.mac();
+        mac.update(&payload);
+        mac.verify_slice(&sig)
+            .map_err(|_| DataAccessErr::InvalidFormat)?;
+        let payload: Payload =
+            serde_json::from_slice(&payload).map_err(|_| DataAccessErr::InvalidFormat)?;
+        if payload.query != query_digest(filter, sort) || payload.after.len() != sort.0.len() {
+            return Err(DataAccessErr::InvalidFormat);
+        }
+        Ok(Cursor {
+            after: payload.after,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, DataAccessErr, FieldInfo, FieldType as FT, FilterNode};
+    use super::super::{test_info, SortItem, SortSpec, StrValue, Value};
+    use super::{Cursor, CursorKey};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let info = |name, type_, sortable| {
+            test_info(name, type_, " Equal Cmp ")
+                .with_nullable(false)
+                .with_sortable(sortable)
+        };
+        field_info_map(vec![
+            info("id", FT::Id, true),
+            info("subscribe_time", FT::DateTime, true),
+            info("nickname", FT::Str, false),
+        ])
+    }
+
+    fn sort() -> SortSpec {
+        SortSpec(vec![SortItem::desc("subscribe_time"), SortItem::asc("id")])
+    }
+
+    #[test]
+    fn round_trip() {
+        let infos = infos();
+        let filter = FilterNode::parse("nickname = 'a'", &infos).unwrap();
+        let row = json!({ "id": 9, "subscribe_time": "2022-01-01 00:00:00", "nickname": "a" });
+        let cursor = Cursor::from_row(&sort(), &row, &infos).unwrap();
+        let time = Value::DateTime(StrValue("2022-01-01 00:00:00".into()));
+        assert_eq!(cursor.after, [time, Value::Id(9)]);
+
+        let key = CursorKey::new(b"secret");
+        let token = key.encode(&cursor, Some(&filter), &sort());
+        let decoded = key.decode(&token, Some(&filter), &sort()).unwrap();
+        assert_eq!(decoded, cursor);
+        assert_eq!(
+            decoded.filter(&sort()).unwrap(),
+            FilterNode::parse(
+                "subscribe_time < '2022-01-01 00:00:00' \
+                 or (subscribe_time = '2022-01-01 00:00:00' and id > 9)",
+                &infos
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn tamper() {
+        let infos = infos();
+        let row = json!({ "id": 9, "subscribe_time": "2022-01-01 00:00:00" });
+        let cursor = Cursor::from_row(&sort(), &row, &infos).unwrap();
+        let key = CursorKey::new(b"secret");
+        let token = key.encode(&cursor, None, &sort());
+
+        let (payload, sig) = token.split_once('.').unwrap();
+        let forged = format!("{}A.{}", payload, sig);
+        assert_eq!(
+            key.decode(&forged, None, &sort()),
+            Err(DataAccessErr::InvalidFormat)
+        );
+        let other = CursorKey::new(b"other");
+        assert_eq!(
+            other.decode(&token, None, &sort()),
+            Err(DataAccessErr::InvalidFormat)
+        );
+        // 换了过滤条件或排序的游标不能继续使用
+        let filter = FilterNode::parse("nickname = 'a'", &infos).unwrap();
+        assert_eq!(
+            key.decode(&token, Some(&filter), &sort()),
+            Err(DataAccessErr::InvalidFormat)
+        );
+        let sort = SortSpec(vec![SortItem::asc("id")]);
+        assert_eq!(
+            key.decode(&token, None, &sort),
+            Err(DataAccessErr::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn from_row() {
+        let infos = infos();
+        let sort = sort();
+        let err = |row| Cursor::from_row(&sort, &row, &infos).unwrap_err();
+        assert_eq!(err(json!({ "id": 9 })), DataAccessErr::InvalidOperation);
+        assert_eq!(
+            err(json!({ "id": "9", "subscribe_time": "" })),
+            DataAccessErr::TypeErr
+        );
+        let sort = SortSpec(vec![SortItem::asc("nickname")]);
+        let row = json!({ "nickname": "a" });
+        assert_eq!(
+            Cursor::from_row(&sort, &row, &infos),
+            Err(DataAccessErr::InvalidOperation)
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use super::DataAccessErr;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value as Json;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+/// 有效数字和小数位数的上限，和数据库 `DECIMAL(38, s)` 一致
+const MAX_DIGITS: u32 = 38;
+
+/// 定点小数 `mantissa / 10^scale`，解析、比较都不经过浮点数，用于金额等字段
+///
+/// 序列化为字符串，反序列化接受字符串和整数，`1.0` 和 `1.00` 相等
+///
+/// 带小数的 json 数字在解析时已经转为浮点数，可能丢失精度，所以不接受，需要用字符串
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+fn pow10(n: u32) -> Option<i128> {
+    10i128.checked_pow(n)
+}
+
+fn scale_up(mantissa: i128, n: u32) -> Option<i128> {
+    pow10(n).and_then(|p| mantissa.checked_mul(p))
+}
+
+impl Decimal {
+    /// 超过 38 位有效数字或小数位数时返回 None
+    pub fn new(mantissa: i128, scale: u32) -> Option<Self> {
+        let max = pow10(MAX_DIGITS)?;
+        (scale <= MAX_DIGITS && mantissa.abs() < max).then_some(Self { mantissa, scale })
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// 按浮点数最短的十进制表示解析，`0.1` 得到 `0.1` 而不是二进制的近似值
+    pub fn from_f64(v: f64) -> Option<Self> {
+        if !v.is_finite() {
+            return None;
+        }
+        v.to_string().parse().ok()
+    }
+
+    /// 小数位数补齐到 `scale`，例如 `18` 为 `18.00`，不会舍入，超出上限时不变
+    pub fn with_scale(self, scale: u32) -> Self {
+        if scale <= self.scale {
+            return self;
+        }
+        scale_up(self.mantissa, scale - self.scale)
+            .and_then(|m| Self::new(m, scale))
+            .unwrap_or(self)
+    }
+
+    /// 最接近的浮点数，只用于和 `Num` 比较
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+
+    /// 数据行中的值，可以是数字或字符串
+    pub(super) fn from_json(v: &Json) -> Option<Self> {
+        match v {
+            Json::Number(n) => n.to_string().parse().ok(),
+            Json::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let (num, exp) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+            None => (s, 0),
+        };
+        let (neg, num) = match num.as_bytes().first() {
+            Some(b'-') => (true, &num[1..]),
+            Some(b'+') => (false, &num[1..]),
+            _ => (false, num),
+        };
+        let (int, frac) = num.split_once('.').unwrap_or((num, ""));
+        let digits = || int.bytes().chain(frac.bytes());
+        if int.len() + frac.len() == 0 || !digits().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let mut mantissa: i128 = 0;
+        for b in digits() {
+            mantissa = mantissa.checked_mul(10)?.checked_add((b - b'0') as i128)?;
+        }
+        let scale = (frac.len() as i64).checked_sub(exp)?;
+        let (mantissa, scale) = match scale {
+            s if s < 0 => (scale_up(mantissa, u32::try_from(-s).ok()?)?, 0),
+            s => (mantissa, u32::try_from(s).ok()?),
+        };
+        Self::new(if neg { -mantissa } else { mantissa }, scale)
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(v: u64) -> Self {
+        Self {
+            mantissa: v as i128,
+            scale: 0,
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = DataAccessErr;
+
+    /// 十进制字符串，可以带符号和指数，例如 `-12.50`、`1e-3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s).ok_or(DataAccessErr::InvalidFormat)
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        // 小数位数少的一边补齐，溢出说明它的绝对值更大，由符号决定大小
+        let align = |l: &Self, r: &Self| match scale_up(l.mantissa, r.scale - l.scale) {
+            Some(m) => m.cmp(&r.mantissa),
+            None => l.mantissa.cmp(&0),
+        };
+        match self.scale.cmp(&rhs.scale) {
+            Ordering::Equal => self.mantissa.cmp(&rhs.mantissa),
+            Ordering::Less => align(self, rhs),
+            Ordering::Greater => align(rhs, self).reverse(),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.cmp(rhs) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct DecimalVisitor;
+
+impl Visitor<'_> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "a decimal string or integer")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Decimal, E> {
+        Decimal::parse(v).ok_or_else(|| E::custom(format!("invalid decimal: {}", v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Decimal, E> {
+        Ok(Decimal {
+            mantissa: v as i128,
+            scale: 0,
+        })
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Decimal, E> {
+        Err(E::custom(format!(
+            "decimal {} must be a string to keep its precision",
+            v
+        )))
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DecimalVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DataAccessErr;
+    use super::Decimal;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let parts = |s: &str| (d(s).mantissa(), d(s).scale());
+        assert_eq!(parts("12.50"), (1250, 2));
+        assert_eq!(parts("-0.05"), (-5, 2));
+        assert_eq!(parts("+.5"), (5, 1));
+        assert_eq!(parts("1.5e3"), (1500, 0));
+        assert_eq!(parts("25e-3"), (25, 3));
+        assert_eq!(
+            parts("12345678901234567890.123456789"),
+            (12345678901234567890123456789, 9)
+        );
+        let invalid = ["", "-", ".", "1.2.3", "1,5", "abc", "1e", "1e99", "0.1e-40"];
+        let overflow = ["1e-9223372036854775808", "1e9223372036854775807"];
+        for s in invalid.into_iter().chain(overflow) {
+            assert_eq!(
+                s.parse::<Decimal>(),
+                Err(DataAccessErr::InvalidFormat),
+                "{}",
+                s
+            );
+        }
+        assert_eq!(Decimal::from_f64(0.1), Some(d("0.1")));
+        assert_eq!(Decimal::from_f64(f64::NAN), None);
+    }
+
+    #[test]
+    fn display_and_cmp() {
+        assert_eq!(d("18").with_scale(2).to_string(), "18.00");
+        assert_eq!(d("-0.05").to_string(), "-0.05");
+        assert_eq!(d("1.005").with_scale(2).to_string(), "1.005");
+        assert_eq!(d("1.0"), d("1.00"));
+        assert!(d("0.3") > d("0.29999999999999999"));
+        assert!(d("0.30000000000000001") > d("0.3"));
+        assert!(d("-1e37") < d("0.00000000000000000000000000000000000001"));
+        assert!(d("99999999999999999999999999999999999999") > d("1.5"));
+    }
+
+    #[test]
+    fn serde() {
+        let v: Vec<Decimal> = serde_json::from_str(r#"["19.99", 18, "0.1", -3]"#).unwrap();
+        assert_eq!(v, [d("19.99"), d("18"), d("0.1"), d("-3")]);
+        assert_eq!(serde_json::to_string(&v[0]).unwrap(), r#""19.99""#);
+        assert!(serde_json::from_str::<Decimal>(r#""1,5""#).is_err());
+
+        // 作为 json 数字时先被解析为浮点数 12345678901234568
+        let long: Decimal = serde_json::from_str(r#""12345678901234567.89""#).unwrap();
+        assert_eq!(long.to_string(), "12345678901234567.89");
+        assert_eq!(
+            serde_json::to_string(&long).unwrap(),
+            r#""12345678901234567.89""#
+        );
+        for json in ["12345678901234567.89", "0.1", "18.0"] {
+            assert!(serde_json::from_str::<Decimal>(json).is_err(), "{}", json);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use super::{
+    Cmp, DataAccessErr, Eq, EvalContext, Field, FieldInfo, FieldType, FilterLimits, FilterNode, In,
+    Like, Logical, Nullable, StrValue, Value,
+};
+use serde_json::{json, Map, Value as Json};
+
+/// `DateTime` 字段的格式，和 [`FilterNode::resolve_time`] 生成的字符串一致
+const DATE_FORMAT: &str = "yyyy-MM-dd HH:mm:ss||strict_date_optional_time";
+
+/// 由字段信息生成 Elasticsearch 的 mapping，
+/// 字符串和枚举为 keyword，保证 term/wildcard 按原值匹配，
+/// 定点小数为 scaled_float，按 `scale` 存为整数
+pub fn es_mapping(infos: &[FieldInfo]) -> Json {
+    let mut props = Map::new();
+    for info in infos {
+        let v = match &info.type_ {
+            FieldType::Str | FieldType::Enum(_) => json!({ "type": "keyword" }),
+            FieldType::Int | FieldType::Id => json!({ "type": "long" }),
+            FieldType::Num => json!({ "type": "double" }),
+            FieldType::Decimal { scale } => {
+                json!({ "type": "scaled_float", "scaling_factor": 10f64.powi(*scale as i32) })
+            }
+            FieldType::DateTime => json!({ "type": "date", "format": DATE_FORMAT }),
+            FieldType::UnixTiemstamp => json!({ "type": "date", "format": "epoch_second" }),
+        };
+        props.insert(info.name.clone(), v);
+    }
+    json!({ "properties": props })
+}
+
+fn range_op(ope: &Cmp) -> &'static str {
+    match ope {
+        Cmp::Gt => "gt",
+        Cmp::GtEq => "gte",
+        Cmp::Lt => "lt",
+        Cmp::LtEq => "lte",
+    }
+}
+
+fn script_op(ope: &str) -> &'static str {
+    match ope {
+        "gt" => "> 0",
+        "gte" => ">= 0",
+        "lt" => "< 0",
+        "lte" => "<= 0",
+        "ne" => "!= 0",
+        _ => "== 0",
+    }
+}
+
+/// 转义 wildcard 的通配符
+fn wildcard_escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\*?".contains(c) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+fn like(ope: &Like, field: &Field, v: &StrValue) -> Json {
+    match ope {
+        Like::StartWith => json!({ "prefix": { field.0.clone(): { "value": v.0 } } }),
+        Like::Contains => {
+            let v = format!("*{}*", wildcard_escape(&v.0));
+            json!({ "wildcard": { field.0.clone(): { "value": v } } })
+        }
+        Like::EndWith => {
+            let v = format!("*{}", wildcard_escape(&v.0));
+            json!({ "wildcard": { field.0.clone(): { "value": v } } })
+        }
+    }
+}
+
+fn exists(field: &Field) -> Json {
+    json!({ "exists": { "field": field.0 } })
+}
+
+fn bool_query(key: &str, v: Vec<Json>) -> Json {
+    json!({ "bool": { key: v } })
+}
+
+/// 字段存在且不满足 `query`，sql 中 NULL 的 `<>`、`NOT IN` 同样不满足
+fn exists_not(field: &Field, query: Json) -> Json {
+    json!({ "bool": { "must": [exists(field)], "must_not": [query] } })
+}
+
+impl EvalContext<'_> {
+    /// 比较条件，引用字段时使用 script，两边都有值时才比较
+    fn es_cmp(
+        &self,
+        field: &Field,
+        conds: &[(&'static str, &Value)],
+    ) -> Result<Json, DataAccessErr> {
+        let info = self
+            .infos
+            .get(&field.0)
+            .ok_or(DataAccessErr::UnknownField)?;
+        let mut range = Map::new();
+        let mut scripts = vec![];
+        for (op, v) in conds {
+            v.check_field_type(&info.type_, self.infos)?;
+            match v {
+                Value::Field(f) => {
+                    let (l, r) = (&field.0, &f.0);
+                    let source = format!(
+                        "doc['{l}'].size() != 0 && doc['{r}'].size() != 0 && \
+                         doc['{l}'].value.compareTo(doc['{r}'].value) {}",
+                        script_op(op)
+                    );
+                    scripts.push(json!({ "script": { "script": { "source": source } } }));
+                }
+                v => {
+                    range.insert(op.to_string(), self.json_value(v, &info.type_)?);
+                }
+            }
+        }
+        let mut must = vec![];
+        if range.contains_key("eq") {
+            must.push(json!({ "term": { field.0.clone(): range["eq"] } }));
+        } else if !range.is_empty() {
+            must.push(json!({ "range": { field.0.clone(): range } }));
+        }
+        must.extend(scripts);
+        Ok(match must.len() {
+            1 => must.pop().unwrap(),
+            _ => bool_query("must", must),
+        })
+    }
+}
+
+impl FilterNode {
+    /// 编译为 Elasticsearch 的 bool 查询，字段需按 [`es_mapping`] 建立索引，
+    /// 先检查 `limits`，再检查同 [`FilterNode::check`]
+    ///
+    /// 先通过 [`FilterNode::push_not`] 下推 Not，`<>`、`NOT IN`、`NOT LIKE`
+    /// 要求字段存在，结果与 sql 的三值逻辑一致
+    pub fn to_es(&self, ctx: &EvalContext, limits: &FilterLimits) -> Result<Json, DataAccessErr> {
+        self.check_limits(limits)?;
+        self.check(ctx.infos)?;
+        self.clone().push_not().es(ctx)
+    }
+
+    fn es(&self, ctx: &EvalContext) -> Result<Json, DataAccessErr> {
+        Ok(match self {
+            FilterNode::Logical(ope, v) => {
+                let v = v.iter().map(|n| n.es(ctx)).collect::<Result<Vec<_>, _>>()?;
+                match (ope, v.is_empty()) {
+                    (Logical::And, true) => json!({ "match_all": {} }),
+                    (Logical::Or, true) => json!({ "match_none": {} }),
+                    (Logical::And, false) => bool_query("must", v),
+                    (Logical::Or, false) => {
+                        json!({ "bool": { "should": v, "minimum_should_match": 1 } })
+                    }
+                }
+            }
+            // push_not 之后只有 Not Like
+            FilterNode::Not(_, n) => match n.as_ref() {
+                FilterNode::Like(ope, field, v) => exists_not(field, like(ope, field, v)),
+                n => bool_query("must_not", vec![n.es(ctx)?]),
+            },
+            FilterNode::Nullable(ope, field) => match ope {
+                Nullable::IsNull => bool_query("must_not", vec![exists(field)]),
+                Nullable::IsNotNull => exists(field),
+            },
+            FilterNode::Equal(ope, field, v) => match (ope, v) {
+                (Eq::Eq, v) => ctx.es_cmp(field, &[("eq", v)])?,
+                (Eq::NotEq, Value::Field(_)) => ctx.es_cmp(field, &[("ne", v)])?,
+                (Eq::NotEq, v) => exists_not(field, ctx.es_cmp(field, &[("eq", v)])?),
+            },
+            FilterNode::Cmp(ope, field, v) => ctx.es_cmp(field, &[(range_op(ope), v)])?,
+            FilterNode::Between(ope, field, lo, hi) => {
+                let (lo_ope, hi_ope) = ope.bounds();
+                ctx.es_cmp(field, &[(range_op(&lo_ope), lo), (range_op(&hi_ope), hi)])?
+            }
+            FilterNode::In(ope, _, v) if v.is_empty() => match ope {
+                In::In => json!({ "match_none": {} }),
+                In::NotIn => json!({ "match_all": {} }),
+            },
+            FilterNode::In(ope, field, v) => {
+                let terms = json!({ "terms": { field.0.clone(): ctx.json_list(field, v)? } });
+                match ope {
+                    In::In => terms,
+                    In::NotIn => exists_not(field, terms),
+                }
+            }
+            FilterNode::Like(ope, field, v) => like(ope, field, v),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, test_info, FilterLimits, FilterNode};
+    use super::super::{Clock, DataAccessErr, EvalContext, FieldInfo, FieldType as FT};
+    use super::es_mapping;
+    use chrono::{FixedOffset, TimeZone, Utc};
+    use serde_json::json;
+
+    fn infos() -> Vec<FieldInfo> {
+        vec![
+            test_info("age", FT::Int, " Equal Cmp In "),
+            test_info("nickname", FT::Str, " Equal Like "),
+            test_info("sex", FT::Enum("sex".into()), " Equal In "),
+            test_info("subscribe_time", FT::UnixTiemstamp, " Cmp "),
+            test_info("last_login", FT::DateTime, " Cmp "),
+        ]
+    }
+
+    fn es(src: &str) -> Result<serde_json::Value, DataAccessErr> {
+        let infos = field_info_map(infos());
+        let clock = Clock {
+            now: Utc.with_ymd_and_hms(2022, 3, 15, 18, 30, 0).unwrap(),
+            tz: FixedOffset::east_opt(8 * 3600).unwrap(),
+        };
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 7,
+            clock: &clock,
+        };
+        FilterNode::parse(src, &infos)
+            .unwrap()
+            .to_es(&ctx, &FilterLimits::default())
+    }
+
+    #[test]
+    fn mapping() {
+        assert_eq!(
+            es_mapping(&infos())["properties"],
+            json!({
+                "age": { "type": "long" },
+                "nickname": { "type": "keyword" },
+                "sex": { "type": "keyword" },
+                "subscribe_time": { "type": "date", "format": "epoch_second" },
+                "last_login": {
+                    "type": "date",
+                    "format": "yyyy-MM-dd HH:mm:ss||strict_date_optional_time",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn to_es() {
+        assert_eq!(
+            es("age >= 18 and (sex = 'female' or nickname like '张%')").unwrap(),
+            json!({ "bool": { "must": [
+                { "range": { "age": { "gte": 18 } } },
+                { "bool": { "should": [
+                    { "term": { "sex": "female" } },
+                    { "prefix": { "nickname": { "value": "张" } } },
+                ], "minimum_should_match": 1 } },
+            ] } })
+        );
+        assert_eq!(
+            es("subscribe_time between [relative(-1, Day, Day), current_date)").unwrap(),
+            json!({ "range": { "subscribe_time": { "gte": 1647273600, "lt": 1647360000 } } })
+        );
+        assert_eq!(
+            es("nickname like '%a*b%' or sex in ('m', 'f')").unwrap(),
+            json!({ "bool": { "should": [
+                { "wildcard": { "nickname": { "value": "*a\\*b*" } } },
+                { "terms": { "sex": ["m", "f"] } },
+            ], "minimum_should_match": 1 } })
+        );
+    }
+
+    #[test]
+    fn not() {
+        let exists = |f: &str| json!({ "exists": { "field": f } });
+        assert_eq!(
+            es("not (age in (1, 2) or nickname is null)").unwrap(),
+            json!({ "bool": { "must": [
+                { "bool": {
+                    "must": [exists("age")],
+                    "must_not": [{ "terms": { "age": [1, 2] } }],
+                } },
+                exists("nickname"),
+            ] } })
+        );
+        assert_eq!(
+            es("age in () or not age in ()").unwrap(),
+            json!({ "bool": { "should": [
+                { "match_none": {} },
+                { "match_all": {} },
+            ], "minimum_should_match": 1 } })
+        );
+        assert_eq!(
+            es("not nickname like 'a%'").unwrap(),
+            json!({ "bool": {
+                "must": [exists("nickname")],
+                "must_not": [{ "prefix": { "nickname": { "value": "a" } } }],
+            } })
+        );
+    }
+
+    #[test]
+    fn field_ref() {
+        let source = "doc['subscribe_time'].size() != 0 && doc['last_login'].size() != 0 && \
+                      doc['subscribe_time'].value.compareTo(doc['last_login'].value) > 0";
+        let mut infos = field_info_map(infos());
+        infos.get_mut("last_login").unwrap().type_ = FT::UnixTiemstamp;
+        let node = FilterNode::parse("subscribe_time > last_login", &infos).unwrap();
+        let clock = Clock::system(FixedOffset::east_opt(0).unwrap());
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 0,
+            clock: &clock,
+        };
+        assert_eq!(
+            node.to_es(&ctx, &FilterLimits::default()).unwrap(),
+            json!({ "script": { "script": { "source": source } } })
+        );
+        assert_eq!(es("nickname > 'a'"), Err(DataAccessErr::InvalidOperation));
+    }
+}
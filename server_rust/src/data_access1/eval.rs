@@ -0,0 +1,466 @@
+use super::time::{parse_datetime, resolve_list, resolve_now};
+use super::{
+    Clock, Cmp, DataAccessErr, Decimal, Eq, FieldInfo, FieldType, FilterLimits, FilterNode, In,
+    Like, ListValue, Logical, Nullable, Value,
+};
+use chrono::{DateTime, FixedOffset};
+use serde_json::{Map, Value as Json};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// 一行数据，字段不存在时返回 None，按 NULL 处理
+pub trait Record {
+    fn get(&self, field: &str) -> Option<&Json>;
+}
+
+impl Record for Json {
+    fn get(&self, field: &str) -> Option<&Json> {
+        self.as_object().and_then(|m| m.get(field))
+    }
+}
+
+impl Record for Map<String, Json> {
+    fn get(&self, field: &str) -> Option<&Json> {
+        Map::get(self, field)
+    }
+}
+
+impl Record for HashMap<String, Json> {
+    fn get(&self, field: &str) -> Option<&Json> {
+        HashMap::get(self, field)
+    }
+}
+
+/// 内存中执行过滤条件需要的上下文，对应 sql 中由数据库确定的值
+pub struct EvalContext<'a> {
+    pub infos: &'a HashMap<String, FieldInfo>,
+    pub user_id: u64,
+    pub clock: &'a Clock,
+}
+
+#[derive(Debug, PartialEq)]
+enum Scalar<'a> {
+    Uint(u64),
+    Num(f64),
+    Str(Cow<'a, str>),
+    Dec(Decimal),
+}
+
+impl Scalar<'_> {
+    /// 定点小数字段的单元格可以是数字或字符串
+    fn decimal(&self) -> Result<Decimal, DataAccessErr> {
+        let v = match self {
+            Scalar::Uint(v) => Some(Decimal::from(*v)),
+            Scalar::Num(v) => Decimal::from_f64(*v),
+            Scalar::Str(s) => s.parse().ok(),
+            Scalar::Dec(v) => Some(*v),
+        };
+        v.ok_or(DataAccessErr::TypeErr)
+    }
+
+    /// `DateTime` 字段的单元格可以是任意 ISO-8601 格式，没有时区时按 `tz`
+    fn time(&self, tz: FixedOffset) -> Result<DateTime<FixedOffset>, DataAccessErr> {
+        match self {
+            Scalar::Str(s) => parse_datetime(s, tz),
+            _ => Err(DataAccessErr::TypeErr),
+        }
+    }
+
+    fn cmp(&self, rhs: &Scalar) -> Result<Option<Ordering>, DataAccessErr> {
+        use Scalar::*;
+        Ok(match (self, rhs) {
+            (Dec(_), _) | (_, Dec(_)) => Some(self.decimal()?.cmp(&rhs.decimal()?)),
+            (Uint(l), Uint(r)) => Some(l.cmp(r)),
+            (Uint(l), Num(r)) => (*l as f64).partial_cmp(r),
+            (Num(l), Uint(r)) => l.partial_cmp(&(*r as f64)),
+            (Num(l), Num(r)) => l.partial_cmp(r),
+            (Str(l), Str(r)) => Some(l.cmp(r)),
+            _ => return Err(DataAccessErr::TypeErr),
+        })
+    }
+}
+
+fn cell(v: Option<&Json>) -> Result<Option<Scalar<'_>>, DataAccessErr> {
+    Ok(match v {
+        None | Some(Json::Null) => None,
+        Some(Json::Number(n)) => match n.as_u64() {
+            Some(n) => Some(Scalar::Uint(n)),
+            None => n.as_f64().map(Scalar::Num),
+        },
+        Some(Json::String(s)) => Some(Scalar::Str(Cow::Borrowed(s))),
+        Some(_) => return Err(DataAccessErr::TypeErr),
+    })
+}
+
+/// 三值逻辑的 AND/OR，None 为 UNKNOWN
+fn logical(ope: &Logical, v: impl Iterator<Item = Option<bool>>) -> Option<bool> {
+    let (short, init) = match ope {
+        Logical::And => (false, true),
+        Logical::Or => (true, false),
+    };
+    let mut res = Some(init);
+    for b in v {
+        match b {
+            Some(b) if b == short => return Some(short),
+            None => res = None,
+            _ => {}
+        }
+    }
+    res
+}
+
+impl EvalContext<'_> {
+    fn field_type(&self, field: &str) -> Result<&FieldType, DataAccessErr> {
+        let info = self.infos.get(field).ok_or(DataAccessErr::UnknownField)?;
+        Ok(&info.type_)
+    }
+
+    fn value<'r>(
+        &self,
+        v: &'r Value,
+        ft: &FieldType,
+        row: &'r impl Record,
+    ) -> Result<Option<Scalar<'r>>, DataAccessErr> {
+        v.check_field_type(ft, self.infos)?;
+        let resolved = match v {
+            Value::Id(v) | Value::Int(v) => return Ok(Some(Scalar::Uint(*v))),
+            Value::Num(v) => return Ok(Some(Scalar::Num(*v))),
+            Value::Decimal(v) => return Ok(Some(Scalar::Dec(*v))),
+            Value::Str(v) => return Ok(Some(Scalar::Str(Cow::Borrowed(&v.0)))),
+            Value::CurrentUserId => return Ok(Some(Scalar::Uint(self.user_id))),
+            Value::Field(f) => return cell(row.get(&f.0)),
+            // 时间统一为字段的类型和格式
+            Value::DateTime(_)
+            | Value::UnixTiemstamp(_)
+            | Value::CurrentTime
+            | Value::CurrentDate
+            | Value::RelativeTime(_) => resolve_now(v, ft, self.clock)?,
+        };
+        Ok(match resolved {
+            Value::UnixTiemstamp(v) => Some(Scalar::Uint(v)),
+            Value::DateTime(v) => Some(Scalar::Str(Cow::Owned(v.0))),
+            _ => return Err(DataAccessErr::TypeErr),
+        })
+    }
+
+    fn cmp(
+        &self,
+        field: &str,
+        v: &Value,
+        row: &impl Record,
+        f: impl Fn(Ordering) -> bool,
+    ) -> Result<Option<bool>, DataAccessErr> {
+        let ft = self.field_type(field)?;
+        let (l, r) = match (cell(row.get(field))?, self.value(v, ft, row)?) {
+            (Some(l), Some(r)) => (l, r),
+            _ => return Ok(None),
+        };
+        Ok(self.cmp_as(ft, &l, &r)?.map(f))
+    }
+
+    /// 按字段类型比较，定点小数按数值、`DateTime` 按时间点，而不是字符串，和数据库一致
+    fn cmp_as(
+        &self,
+        ft: &FieldType,
+        l: &Scalar,
+        r: &Scalar,
+    ) -> Result<Option<Ordering>, DataAccessErr> {
+        let tz = self.clock.tz;
+        Ok(match ft {
+            FieldType::Decimal { .. } => Some(l.decimal()?.cmp(&r.decimal()?)),
+            FieldType::DateTime => Some(l.time(tz)?.cmp(&r.time(tz)?)),
+            _ => l.cmp(r)?,
+        })
+    }
+
+    fn list_contains(
+        &self,
+        ft: &FieldType,
+        x: &Scalar,
+        v: &ListValue,
+    ) -> Result<bool, DataAccessErr> {
+        for r in list_iter(v) {
+            if self.cmp_as(ft, x, &r)? == Some(Ordering::Equal) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn cmp_fn(ope: &Cmp) -> fn(Ordering) -> bool {
+    match ope {
+        Cmp::Gt => Ordering::is_gt,
+        Cmp::GtEq => Ordering::is_ge,
+        Cmp::Lt => Ordering::is_lt,
+        Cmp::LtEq => Ordering::is_le,
+    }
+}
+
+fn list_iter(v: &ListValue) -> Box<dyn Iterator<Item = Scalar<'_>> + '_> {
+    match v {
+        ListValue::Id(v) | ListValue::Int(v) | ListValue::UnixTiemstamp(v) => {
+            Box::new(v.iter().map(|v| Scalar::Uint(*v)))
+        }
+        ListValue::Num(v) => Box::new(v.iter().map(|v| Scalar::Num(*v))),
+        ListValue::Decimal(v) => Box::new(v.iter().map(|v| Scalar::Dec(*v))),
+        ListValue::Str(v) | ListValue::DateTime(v) => {
+            Box::new(v.iter().map(|v| Scalar::Str(Cow::Borrowed(&v.0))))
+        }
+    }
+}
+
+impl FilterNode {
+    /// 在内存中按 sql 的三值逻辑计算，`Ok(None)` 为 UNKNOWN (NULL)
+    ///
+    /// 只检查值的类型，不检查复杂度和字段是否允许该操作，调用前要先
+    /// [`FilterNode::check_limits`] 和 [`FilterNode::check`]，或者用 [`FilterNode::matches`]
+    pub fn eval(
+        &self,
+        row: &impl Record,
+        ctx: &EvalContext,
+    ) -> Result<Option<bool>, DataAccessErr> {
+        Ok(match self {
+            FilterNode::Logical(ope, v) => {
+                let v = v
+                    .iter()
+                    .map(|n| n.eval(row, ctx))
+                    .collect::<Result<Vec<_>, _>>()?;
+                logical(ope, v.into_iter())
+            }
+            FilterNode::Not(_, n) => n.eval(row, ctx)?.map(|b| !b),
+            FilterNode::Nullable(ope, field) => {
+                ctx.field_type(&field.0)?;
+                let is_null = cell(row.get(&field.0))?.is_none();
+                Some(match ope {
+                    Nullable::IsNull => is_null,
+                    Nullable::IsNotNull => !is_null,
+                })
+            }
+            FilterNode::Equal(ope, field, v) => match ope {
+                Eq::Eq => ctx.cmp(&field.0, v, row, Ordering::is_eq)?,
+                Eq::NotEq => ctx.cmp(&field.0, v, row, Ordering::is_ne)?,
+            },
+            FilterNode::Cmp(ope, field, v) => ctx.cmp(&field.0, v, row, cmp_fn(ope))?,
+            FilterNode::Between(ope, field, lo, hi) => {
+                let (lo_ope, hi_ope) = ope.bounds();
+                let lo = ctx.cmp(&field.0, lo, row, cmp_fn(&lo_ope))?;
+                let hi = ctx.cmp(&field.0, hi, row, cmp_fn(&hi_ope))?;
+                logical(&Logical::And, [lo, hi].into_iter())
+            }
+            FilterNode::In(ope, field, v) => {
+                let ft = ctx.field_type(&field.0)?;
+                v.chekc_type(ft)?;
+                let v = &resolve_list(v, ft, ctx.clock)?;
+                if v.is_empty() {
+                    return Ok(Some(*ope == In::NotIn));
+                }
+                match cell(row.get(&field.0))? {
+                    None => None,
+                    Some(x) => Some(match ope {
+                        In::In => ctx.list_contains(ft, &x, v)?,
+                        In::NotIn => !ctx.list_contains(ft, &x, v)?,
+                    }),
+                }
+            }
+            FilterNode::Like(ope, field, v) => {
+                match ctx.field_type(&field.0)? {
+                    FieldType::Str => {}
+                    _ => return Err(DataAccessErr::TypeErr),
+                }
+                match cell(row.get(&field.0))? {
+                    None => None,
+                    Some(Scalar::Str(s)) => Some(match ope {
+                        Like::StartWith => s.starts_with(&v.0),
+                        Like::Contains => s.contains(&v.0),
+                        Like::EndWith => s.ends_with(&v.0),
+                    }),
+                    Some(_) => return Err(DataAccessErr::TypeErr),
+                }
+            }
+        })
+    }
+
+    /// 同 sql 的 WHERE，UNKNOWN 视为不满足
+    ///
+    /// 和 [`FilterNode::to_sql`] 一样先检查复杂度和字段；过滤多行时可以先检查一次，
+    /// 再对每一行调用 [`FilterNode::eval`]
+    pub fn matches(
+        &self,
+        row: &impl Record,
+        ctx: &EvalContext,
+        limits: &FilterLimits,
+    ) -> Result<bool, DataAccessErr> {
+        self.check_limits(limits)?;
+        self.check(ctx.infos)?;
+        Ok(self.eval(row, ctx)? == Some(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::FilterNode;
+    use super::super::{field_info_map, Clock, DataAccessErr, FieldInfo, FieldType as FT};
+    use super::super::{test_info, FilterLimits, LimitExceeded, LimitKind};
+    use super::EvalContext;
+    use chrono::{FixedOffset, TimeZone, Utc};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let info = |name, type_| test_info(name, type_, " Equal Cmp In Like ");
+        field_info_map(vec![
+            info("id", FT::Id),
+            info("age", FT::Int),
+            info("sex", FT::Enum("sex".into())),
+            info("name", FT::Str),
+            info("owner_id", FT::Id),
+            info("reg_time", FT::DateTime),
+            info("amount", FT::Decimal { scale: 2 }),
+        ])
+    }
+
+    fn clock() -> Clock {
+        let now = Utc.with_ymd_and_hms(2022, 3, 15, 18, 30, 0).unwrap();
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        Clock { now, tz }
+    }
+
+    fn ids(src: &str, rows: &[serde_json::Value]) -> Vec<u64> {
+        let (infos, clock) = (infos(), clock());
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 2,
+            clock: &clock,
+        };
+        let node = FilterNode::parse(src, &infos).unwrap();
+        let limits = FilterLimits::default();
+        let rows = rows
+            .iter()
+            .filter(|row| node.matches(*row, &ctx, &limits).unwrap());
+        rows.map(|row| row["id"].as_u64().unwrap()).collect()
+    }
+
+    fn rows() -> Vec<serde_json::Value> {
+        vec![
+            json!({ "id": 1, "age": 17, "sex": "female", "name": "Zhang", "owner_id": 2, "reg_time": "2022-03-16 01:00:00" }),
+            json!({ "id": 2, "age": 30, "sex": "male", "name": "Li", "owner_id": 1, "reg_time": "2022-01-01 00:00:00" }),
+            json!({ "id": 3, "age": null, "sex": "female", "name": null, "owner_id": 3 }),
+        ]
+    }
+
+    #[test]
+    fn eval() {
+        let rows = &rows();
+        assert_eq!(ids("age >= 18 or sex = 'female'", rows), [1, 2, 3]);
+        assert_eq!(
+            ids("age >= 18 and (sex = 'female' or name like 'L%')", rows),
+            [2]
+        );
+        assert_eq!(ids("id in (1, 3) and not id in ()", rows), [1, 3]);
+        assert_eq!(
+            ids("owner_id = current_user_id or owner_id = id", rows),
+            [1, 3]
+        );
+        assert_eq!(ids("reg_time >= current_date", rows), [1]);
+        assert_eq!(
+            ids("reg_time between [relative(-3, Month), current_time)", rows),
+            [1, 2]
+        );
+        assert_eq!(ids("name is null", rows), [3]);
+    }
+
+    #[test]
+    fn three_valued() {
+        let rows = &rows();
+        // age 为 NULL 时，age > 18 和 NOT (age > 18) 都不满足
+        assert_eq!(ids("age > 18", rows), [2]);
+        assert_eq!(ids("not age > 18", rows), [1]);
+        assert_eq!(ids("age not in (17)", rows), [2]);
+        assert_eq!(ids("age > 18 or id = 3", rows), [2, 3]);
+        assert_eq!(ids("not (age > 18 and id = 3)", rows), [1, 2]);
+        // 空列表是常量，和字段是否为 NULL 无关
+        assert!(ids("age in ()", rows).is_empty());
+        assert_eq!(ids("age not in ()", rows), [1, 2, 3]);
+        assert_eq!(ids("not age in ()", rows), [1, 2, 3]);
+    }
+
+    #[test]
+    fn datetime_formats() {
+        // 按时间点比较，不同格式、时区的单元格和 sql 的结果一致，current_date 为 +08:00 的 2022-03-16
+        let rows = &[
+            json!({ "id": 1, "reg_time": "2022-03-16" }),
+            json!({ "id": 2, "reg_time": "2022-03-15T17:00:00Z" }),
+            json!({ "id": 3, "reg_time": "2022-03-16T01:00:00+09:00" }),
+            json!({ "id": 4, "reg_time": "2022-03-15 23:59:59" }),
+        ];
+        assert_eq!(ids("reg_time >= current_date", rows), [1, 2, 3]);
+        assert_eq!(ids("reg_time > current_date", rows), [2]);
+        assert_eq!(ids("reg_time in ('2022-03-16 00:00:00')", rows), [1, 3]);
+    }
+
+    #[test]
+    fn decimal() {
+        // 金额可以是字符串或数字，按小数精确比较，0.1 + 0.2 的浮点数不等于 0.3
+        let rows = &[
+            json!({ "id": 1, "amount": "0.30" }),
+            json!({ "id": 2, "amount": 0.3 }),
+            json!({ "id": 3, "amount": 0.1 + 0.2 }),
+            json!({ "id": 4, "amount": "18" }),
+        ];
+        assert_eq!(ids("amount = 0.3", rows), [1, 2]);
+        assert_eq!(ids("amount > 0.3", rows), [3, 4]);
+        assert_eq!(ids("amount in (0.30, 18.00)", rows), [1, 2, 4]);
+        assert_eq!(ids("amount between [0.3, 18)", rows), [1, 2, 3]);
+    }
+
+    #[test]
+    fn type_err() {
+        let (infos, clock) = (infos(), clock());
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 2,
+            clock: &clock,
+        };
+        let node: FilterNode =
+            serde_json::from_str(r#"["Gt","age",{"type":"Num","value":1}]"#).unwrap();
+        assert_eq!(node.eval(&rows()[0], &ctx), Err(DataAccessErr::TypeErr));
+        let node = FilterNode::parse("age > 1", &infos).unwrap();
+        let row = json!({ "age": "1" });
+        assert_eq!(node.eval(&row, &ctx), Err(DataAccessErr::TypeErr));
+    }
+
+    #[test]
+    fn matches_checks_first() {
+        let (mut infos, clock) = (infos(), clock());
+        infos.get_mut("name").unwrap().operation = " Equal ".into();
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 2,
+            clock: &clock,
+        };
+        let row = &rows()[0];
+        let node = FilterNode::parse("name like 'Z%'", &infos).unwrap();
+        assert_eq!(node.eval(row, &ctx), Ok(Some(true)));
+        let limits = FilterLimits::default();
+        assert_eq!(
+            node.matches(row, &ctx, &limits),
+            Err(DataAccessErr::InvalidOperation)
+        );
+
+        let node = FilterNode::parse("not not age = 17", &infos).unwrap();
+        let limits = FilterLimits {
+            max_depth: 2,
+            ..FilterLimits::default()
+        };
+        assert_eq!(
+            node.matches(row, &ctx, &limits),
+            Err(DataAccessErr::TooComplex(LimitExceeded {
+                kind: LimitKind::Depth,
+                limit: 2,
+                actual: 3,
+            }))
+        );
+    }
+}
@@ -0,0 +1,817 @@
+//! 过滤条件的文本表达式
+//!
+//! `age >= 18 and (sex = 'female' or name like 'Zh%')`
+//!
+//! - 比较：`=` `!=` `<>` `>` `>=` `<` `<=`
+//! - 范围：`x between 1 and 2`，`x between [1, 2)`，`x between (1, 2]`
+//! - 列表：`x in (1, 2)`，`x not in ('a', 'b')`
+//! - 空值：`x is null`，`x is not null`
+//! - 模糊：`x like 'a%'`，`'%a%'`，`'%a'`，`\%` 表示 `%` 本身
+//! - 值：数字、`'字符串'` (`''` 表示 `'`)、`current_user_id`、`current_date`、
+//!   `current_time`、`relative(-30, Day, Day)`，其他标识符为字段引用
+//!
+//! 字面量的类型由左边字段的 [`FieldType`] 决定，所以解析时需要字段信息；
+//! 时间字段既可以写 `'2022-03-15'` 也可以写时间戳 `1647273600`
+
+use super::{
+    Between, Cmp, Decimal, Eq, Field, FieldInfo, FieldType, FilterLimits, FilterNode, In, Like,
+    ListValue, Logical, Not, Nullable, RelativeTime, StrValue, TimeUnit, Value,
+};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrKind {
+    UnexpectedChar,
+    UnterminatedString,
+    UnexpectedToken,
+    UnexpectedEnd,
+    InvalidNumber,
+    InvalidLike,
+    UnknownField,
+    TypeErr,
+    /// `not` 和括号嵌套超过 [`FilterLimits::max_depth`]
+    TooDeep,
+}
+
+/// 解析错误，`pos` 为出错位置的字符序号（从 0 开始）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseErr {
+    pub pos: usize,
+    pub kind: ParseErrKind,
+}
+
+impl Display for ParseErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:?} at {}", self.kind, self.pos)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Str(String),
+    Num(String),
+    Sym(&'static str),
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    toks: Vec<(usize, Tok)>,
+}
+
+const SYMS: [&str; 12] = [
+    ">=", "<=", "!=", "<>", "=", ">", "<", "(", ")", "[", "]", ",",
+];
+
+impl<'a> Lexer<'a> {
+    fn err(&self, byte: usize, kind: ParseErrKind) -> ParseErr {
+        let pos = self.src[..byte].chars().count();
+        ParseErr { pos, kind }
+    }
+
+    fn lex(mut self) -> Result<Vec<(usize, Tok)>, ParseErr> {
+        let src = self.src;
+        let mut i = 0;
+        while i < src.len() {
+            let rest = &src[i..];
+            let c = rest.chars().next().unwrap();
+            if c.is_whitespace() {
+                i += c.len_utf8();
+            } else if c == '\'' {
+                let mut s = String::new();
+                let mut end = None;
+                let mut chars = rest.char_indices().skip(1).peekable();
+                while let Some((j, c)) = chars.next() {
+                    if c != '\'' {
+                        s.push(c);
+                    } else if chars.peek().map(|(_, c)| *c) == Some('\'') {
+                        s.push('\'');
+                        chars.next();
+                    } else {
+                        end = Some(j + 1);
+                        break;
+                    }
+                }
+                let end = end.ok_or_else(|| self.err(i, ParseErrKind::UnterminatedString))?;
+                self.toks.push((i, Tok::Str(s)));
+                i += end;
+            } else if c.is_ascii_digit()
+                || (c == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+            {
+                let len = 1 + rest[1..]
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .unwrap_or(rest.len() - 1);
+                self.toks.push((i, Tok::Num(rest[..len].to_string())));
+                i += len;
+            } else if c.is_alphabetic() || c == '_' {
+                let len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                self.toks.push((i, Tok::Ident(rest[..len].to_string())));
+                i += len;
+            } else {
+                let sym = SYMS.iter().find(|s| rest.starts_with(**s));
+                let sym = sym.ok_or_else(|| self.err(i, ParseErrKind::UnexpectedChar))?;
+                self.toks.push((i, Tok::Sym(sym)));
+                i += sym.len();
+            }
+        }
+        Ok(self.toks)
+    }
+}
+
+const KEYWORDS: [&str; 12] = [
+    "and",
+    "or",
+    "not",
+    "in",
+    "is",
+    "null",
+    "like",
+    "between",
+    "relative",
+    "current_user_id",
+    "current_date",
+    "current_time",
+];
+
+struct Parser<'a> {
+    src: &'a str,
+    toks: Vec<(usize, Tok)>,
+    i: usize,
+    infos: &'a HashMap<String, FieldInfo>,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// 第 i 个 token 的错误，超出末尾时为 `UnexpectedEnd`
+    fn err_at(&self, i: usize, kind: ParseErrKind) -> ParseErr {
+        let (byte, kind) = match self.toks.get(i) {
+            Some(t) => (t.0, kind),
+            None => (self.src.len(), ParseErrKind::UnexpectedEnd),
+        };
+        let pos = self.src[..byte].chars().count();
+        ParseErr { pos, kind }
+    }
+
+    fn err(&self) -> ParseErr {
+        self.err_at(self.i, ParseErrKind::UnexpectedToken)
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.i).map(|t| &t.1)
+    }
+
+    fn next(&mut self) -> Option<Tok> {
+        let tok = self.toks.get(self.i).map(|t| t.1.clone());
+        self.i += 1;
+        tok
+    }
+
+    fn is_keyword(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(s)) if s.eq_ignore_ascii_case(kw))
+    }
+
+    fn eat_keyword(&mut self, kw: &str) -> bool {
+        let is = self.is_keyword(kw);
+        if is {
+            self.i += 1;
+        }
+        is
+    }
+
+    fn keyword(&mut self, kw: &str) -> Result<(), ParseErr> {
+        self.eat_keyword(kw).then_some(()).ok_or_else(|| self.err())
+    }
+
+    fn eat_sym(&mut self, sym: &str) -> bool {
+        let is = matches!(self.peek(), Some(Tok::Sym(s)) if *s == sym);
+        if is {
+            self.i += 1;
+        }
+        is
+    }
+
+    fn sym(&mut self, sym: &str) -> Result<(), ParseErr> {
+        self.eat_sym(sym).then_some(()).ok_or_else(|| self.err())
+    }
+
+    fn or(&mut self) -> Result<FilterNode, ParseErr> {
+        self.logical(Logical::Or, "or", Self::and)
+    }
+
+    fn and(&mut self) -> Result<FilterNode, ParseErr> {
+        self.logical(Logical::And, "and", Self::unary)
+    }
+
+    fn logical(
+        &mut self,
+        ope: Logical,
+        kw: &str,
+        next: fn(&mut Self) -> Result<FilterNode, ParseErr>,
+    ) -> Result<FilterNode, ParseErr> {
+        let mut v = vec![next(self)?];
+        while self.eat_keyword(kw) {
+            v.push(next(self)?);
+        }
+        match v.len() {
+            1 => Ok(v.pop().unwrap()),
+            _ => Ok(FilterNode::Logical(ope, v)),
+        }
+    }
+
+    fn unary(&mut self) -> Result<FilterNode, ParseErr> {
+        let i = self.i;
+        if self.eat_keyword("not") {
+            let node = self.nested(i, Self::unary)?;
+            return Ok(FilterNode::Not(Not::Not, Box::new(node)));
+        }
+        if self.eat_sym("(") {
+            let node = self.nested(i, Self::or)?;
+            self.sym(")")?;
+            return Ok(node);
+        }
+        self.predicate()
+    }
+
+    /// 第 i 个 token 开始的一层嵌套，超过 `max_depth` 时报错，递归深度不会超过它
+    fn nested(
+        &mut self,
+        i: usize,
+        next: fn(&mut Self) -> Result<FilterNode, ParseErr>,
+    ) -> Result<FilterNode, ParseErr> {
+        if self.depth >= self.max_depth {
+            return Err(self.err_at(i, ParseErrKind::TooDeep));
+        }
+        self.depth += 1;
+        let node = next(self);
+        self.depth -= 1;
+        node
+    }
+
+    fn field(&mut self) -> Result<(Field, &'a FieldInfo), ParseErr> {
+        let i = self.i;
+        let name = match self.next() {
+            Some(Tok::Ident(name)) if !KEYWORDS.contains(&name.to_lowercase().as_str()) => name,
+            _ => return Err(self.err_at(i, ParseErrKind::UnexpectedToken)),
+        };
+        let infos = self.infos;
+        let info = infos.get(&name);
+        let info = info.ok_or_else(|| self.err_at(i, ParseErrKind::UnknownField))?;
+        Ok((Field(name), info))
+    }
+
+    fn predicate(&mut self) -> Result<FilterNode, ParseErr> {
+        let (field, info) = self.field()?;
+        let ft = &info.type_;
+        if self.eat_keyword("is") {
+            let ope = match self.eat_keyword("not") {
+                true => Nullable::IsNotNull,
+                false => Nullable::IsNull,
+            };
+            self.keyword("null")?;
+            return Ok(FilterNode::Nullable(ope, field));
+        }
+        let not = self.eat_keyword("not");
+        if self.eat_keyword("in") {
+            let ope = if not { In::NotIn } else { In::In };
+            return Ok(FilterNode::In(ope, field, self.list(ft)?));
+        }
+        if self.eat_keyword("like") {
+            let i = self.i;
+            let like = match self.next() {
+                Some(Tok::Str(s)) => like_pattern(&s),
+                _ => None,
+            };
+            let (ope, v) = like.ok_or_else(|| self.err_at(i, ParseErrKind::InvalidLike))?;
+            let node = FilterNode::Like(ope, field, StrValue(v));
+            return Ok(if not { !node } else { node });
+        }
+        if self.eat_keyword("between") {
+            let node = self.between(field, ft)?;
+            return Ok(if not { !node } else { node });
+        }
+        if not {
+            return Err(self.err());
+        }
+        let ope = match self.next() {
+            Some(Tok::Sym("=")) => Ok(Eq::Eq),
+            Some(Tok::Sym("!=" | "<>")) => Ok(Eq::NotEq),
+            Some(Tok::Sym(">")) => Err(Cmp::Gt),
+            Some(Tok::Sym(">=")) => Err(Cmp::GtEq),
+            Some(Tok::Sym("<")) => Err(Cmp::Lt),
+            Some(Tok::Sym("<=")) => Err(Cmp::LtEq),
+            _ => {
+                self.i -= 1;
+                return Err(self.err());
+            }
+        };
+        let v = self.value(ft)?;
+        Ok(match ope {
+            Ok(ope) => FilterNode::Equal(ope, field, v),
+            Err(ope) => FilterNode::Cmp(ope, field, v),
+        })
+    }
+
+    fn between(&mut self, field: Field, ft: &FieldType) -> Result<FilterNode, ParseErr> {
+        let lo_open = if self.eat_sym("[") {
+            Some(false)
+        } else if self.eat_sym("(") {
+            Some(true)
+        } else {
+            None
+        };
+        let lo = self.value(ft)?;
+        let lo_open = match lo_open {
+            None => {
+                self.keyword("and")?;
+                let hi = self.value(ft)?;
+                return Ok(FilterNode::Between(Between::Between, field, lo, hi));
+            }
+            Some(open) => open,
+        };
+        self.sym(",")?;
+        let hi = self.value(ft)?;
+        let i = self.i;
+        let ope = match (lo_open, self.next()) {
+            (false, Some(Tok::Sym(")"))) => Between::GtEqLt,
+            (true, Some(Tok::Sym("]"))) => Between::GtLtEq,
+            (false, Some(Tok::Sym("]"))) => Between::Between,
+            _ => return Err(self.err_at(i, ParseErrKind::UnexpectedToken)),
+        };
+        Ok(FilterNode::Between(ope, field, lo, hi))
+    }
+
+    fn list(&mut self, ft: &FieldType) -> Result<ListValue, ParseErr> {
+        self.sym("(")?;
+        let mut v = vec![];
+        if !self.eat_sym(")") {
+            loop {
+                v.push((self.i, self.next()));
+                if self.eat_sym(")") {
+                    break;
+                }
+                self.sym(",")?;
+            }
+        }
+        let num = |(i, tok): (usize, Option<Tok>)| match tok {
+            Some(Tok::Num(n)) => Ok(n),
+            _ => Err(self.err_at(i, ParseErrKind::TypeErr)),
+        };
+        let str = |(i, tok): (usize, Option<Tok>)| match tok {
+            Some(Tok::Str(s)) => Ok(StrValue(s)),
+            _ => Err(self.err_at(i, ParseErrKind::TypeErr)),
+        };
+        let uint = |t: (usize, Option<Tok>)| {
+            let i = t.0;
+            let n = num(t)?;
+            n.parse::<u64>()
+                .map_err(|_| self.err_at(i, ParseErrKind::InvalidNumber))
+        };
+        let float = |t: (usize, Option<Tok>)| {
+            let i = t.0;
+            let n = num(t)?;
+            n.parse::<f64>()
+                .map_err(|_| self.err_at(i, ParseErrKind::InvalidNumber))
+        };
+        let decimal = |t: (usize, Option<Tok>)| {
+            let i = t.0;
+            let n = num(t)?;
+            n.parse::<Decimal>()
+                .map_err(|_| self.err_at(i, ParseErrKind::InvalidNumber))
+        };
+        // 时间字段可以写时间字符串或时间戳，枚举可以写整数或字符串，由第一个元素决定
+        let first_str = match v.first() {
+            Some((_, tok)) => matches!(tok, Some(Tok::Str(_))),
+            None => *ft != FieldType::UnixTiemstamp,
+        };
+        let v = v.into_iter();
+        Ok(match ft {
+            FieldType::Enum(_) if !first_str => {
+                ListValue::Int(v.map(uint).collect::<Result<_, _>>()?)
+            }
+            FieldType::Str | FieldType::Enum(_) => {
+                ListValue::Str(v.map(str).collect::<Result<_, _>>()?)
+            }
+            FieldType::DateTime | FieldType::UnixTiemstamp if first_str => {
+                ListValue::DateTime(v.map(str).collect::<Result<_, _>>()?)
+            }
+            FieldType::DateTime | FieldType::UnixTiemstamp => {
+                ListValue::UnixTiemstamp(v.map(uint).collect::<Result<_, _>>()?)
+            }
+            FieldType::Id => ListValue::Id(v.map(uint).collect::<Result<_, _>>()?),
+            FieldType::Int => ListValue::Int(v.map(uint).collect::<Result<_, _>>()?),
+            FieldType::Num => ListValue::Num(v.map(float).collect::<Result<_, _>>()?),
+            FieldType::Decimal { .. } => {
+                ListValue::Decimal(v.map(decimal).collect::<Result<_, _>>()?)
+            }
+        })
+    }
+
+    fn value(&mut self, ft: &FieldType) -> Result<Value, ParseErr> {
+        let i = self.i;
+        let type_err = |p: &Self| p.err_at(i, ParseErrKind::TypeErr);
+        let invalid_num = |p: &Self| p.err_at(i, ParseErrKind::InvalidNumber);
+        match self.next() {
+            Some(Tok::Num(n)) => match ft {
+                FieldType::Num => n.parse().map(Value::Num).map_err(|_| invalid_num(self)),
+                FieldType::Decimal { .. } => {
+                    n.parse().map(Value::Decimal).map_err(|_| invalid_num(self))
+                }
+                FieldType::Id
+                | FieldType::Int
+                | FieldType::DateTime
+                | FieldType::UnixTiemstamp
+                | FieldType::Enum(_) => {
+                    let n = n.parse::<u64>().map_err(|_| invalid_num(self))?;
+                    Ok(match ft {
+                        FieldType::Id => Value::Id(n),
+                        FieldType::DateTime | FieldType::UnixTiemstamp => Value::UnixTiemstamp(n),
+                        _ => Value::Int(n),
+                    })
+                }
+                _ => Err(type_err(self)),
+            },
+            Some(Tok::Str(s)) => match ft {
+                FieldType::Str | FieldType::Enum(_) => Ok(Value::Str(StrValue(s))),
+                FieldType::DateTime | FieldType::UnixTiemstamp => Ok(Value::DateTime(StrValue(s))),
+                _ => Err(type_err(self)),
+            },
+            Some(Tok::Ident(s)) => match s.to_lowercase().as_str() {
+                "current_user_id" => Ok(Value::CurrentUserId),
+                "current_date" => Ok(Value::CurrentDate),
+                "current_time" => Ok(Value::CurrentTime),
+                "relative" => self.relative_time(),
+                _ => {
+                    self.i -= 1;
+                    Ok(Value::Field(self.field()?.0))
+                }
+            },
+            _ => {
+                self.i -= 1;
+                Err(self.err())
+            }
+        }
+    }
+
+    fn relative_time(&mut self) -> Result<Value, ParseErr> {
+        self.sym("(")?;
+        let i = self.i;
+        let offset = match self.next() {
+            Some(Tok::Num(n)) => n.parse::<i32>().ok(),
+            _ => None,
+        };
+        let offset = offset.ok_or_else(|| self.err_at(i, ParseErrKind::InvalidNumber))?;
+        self.sym(",")?;
+        let unit = self.time_unit()?;
+        let trunc = match self.eat_sym(",") {
+            true => Some(self.time_unit()?),
+            false => None,
+        };
+        self.sym(")")?;
+        Ok(Value::RelativeTime(RelativeTime {
+            offset,
+            unit,
+            trunc,
+        }))
+    }
+
+    fn time_unit(&mut self) -> Result<TimeUnit, ParseErr> {
+        use TimeUnit::*;
+        let i = self.i;
+        let unit = match self.next() {
+            Some(Tok::Ident(s)) => match s.to_lowercase().as_str() {
+                "second" => Some(Second),
+                "minute" => Some(Minute),
+                "hour" => Some(Hour),
+                "day" => Some(Day),
+                "week" => Some(Week),
+                "month" => Some(Month),
+                "year" => Some(Year),
+                _ => None,
+            },
+            _ => None,
+        };
+        unit.ok_or_else(|| self.err_at(i, ParseErrKind::UnexpectedToken))
+    }
+}
+
+/// `'a%'` => StartWith，`'%a%'` => Contains，`'%a'` => EndWith
+fn like_pattern(s: &str) -> Option<(Like, String)> {
+    let mut v = String::new();
+    let mut wildcard = vec![];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => v.push(chars.next()?),
+            '%' => wildcard.push(v.chars().count()),
+            c => v.push(c),
+        }
+    }
+    let len = v.chars().count();
+    let ope = match wildcard[..] {
+        [0, n] if n == len => Like::Contains,
+        [n] if n == len => Like::StartWith,
+        [0] => Like::EndWith,
+        _ => return None,
+    };
+    Some((ope, v))
+}
+
+impl FilterNode {
+    /// 解析文本表达式，字面量按字段类型转为对应的 [`Value`]，嵌套层数按默认的 [`FilterLimits`]
+    pub fn parse(src: &str, infos: &HashMap<String, FieldInfo>) -> Result<FilterNode, ParseErr> {
+        Self::parse_with_limits(src, infos, &FilterLimits::default())
+    }
+
+    /// 同 [`FilterNode::parse`]，`not` 和括号的嵌套不能超过 `limits.max_depth`
+    ///
+    /// 只限制解析时的递归深度，其他限制仍由 [`FilterNode::check_limits`] 检查
+    pub fn parse_with_limits(
+        src: &str,
+        infos: &HashMap<String, FieldInfo>,
+        limits: &FilterLimits,
+    ) -> Result<FilterNode, ParseErr> {
+        let toks = Lexer { src, toks: vec![] }.lex()?;
+        let mut p = Parser {
+            src,
+            toks,
+            i: 0,
+            infos,
+            depth: 0,
+            max_depth: limits.max_depth,
+        };
+        let node = p.or()?;
+        match p.i < p.toks.len() {
+            true => Err(p.err()),
+            false => Ok(node),
+        }
+    }
+
+    /// 文本表达式，可以被 [`FilterNode::parse`] 解析回来
+    pub fn expr(&self) -> FilterExpr<'_> {
+        FilterExpr(self)
+    }
+}
+
+pub struct FilterExpr<'a>(&'a FilterNode);
+
+fn write_str(f: &mut Formatter<'_>, s: &str) -> FmtResult {
+    write!(f, "'{}'", s.replace('\'', "''"))
+}
+
+fn write_value(f: &mut Formatter<'_>, v: &Value) -> FmtResult {
+    match v {
+        Value::Id(v) | Value::Int(v) | Value::UnixTiemstamp(v) => write!(f, "{}", v),
+        Value::Num(v) => write!(f, "{}", v),
+        Value::Decimal(v) => write!(f, "{}", v),
+        Value::Str(v) | Value::DateTime(v) => write_str(f, &v.0),
+        Value::Field(v) => write!(f, "{}", v),
+        Value::CurrentUserId => write!(f, "current_user_id"),
+        Value::CurrentDate => write!(f, "current_date"),
+        Value::CurrentTime => write!(f, "current_time"),
+        Value::RelativeTime(rt) => match rt.trunc {
+            Some(trunc) => write!(f, "relative({}, {:?}, {:?})", rt.offset, rt.unit, trunc),
+            None => write!(f, "relative({}, {:?})", rt.offset, rt.unit),
+        },
+    }
+}
+
+fn write_list(f: &mut Formatter<'_>, v: &ListValue) -> FmtResult {
+    fn join<T>(
+        f: &mut Formatter<'_>,
+        v: &[T],
+        w: impl Fn(&mut Formatter<'_>, &T) -> FmtResult,
+    ) -> FmtResult {
+        write!(f, "(")?;
+        for (i, x) in v.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            w(f, x)?;
+        }
+        write!(f, ")")
+    }
+    match v {
+        ListValue::Id(v) | ListValue::Int(v) | ListValue::UnixTiemstamp(v) => {
+            join(f, v, |f, x| write!(f, "{}", x))
+        }
+        ListValue::Num(v) => join(f, v, |f, x| write!(f, "{}", x)),
+        ListValue::Decimal(v) => join(f, v, |f, x| write!(f, "{}", x)),
+        ListValue::Str(v) | ListValue::DateTime(v) => join(f, v, |f, x| write_str(f, &x.0)),
+    }
+}
+
+fn write_node(f: &mut Formatter<'_>, node: &FilterNode, nested: bool) -> FmtResult {
+    match node {
+        FilterNode::Logical(ope, v) => {
+            let sep = match ope {
+                Logical::And => " and ",
+                Logical::Or => " or ",
+            };
+            if nested || v.is_empty() {
+                write!(f, "(")?;
+            }
+            for (i, n) in v.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "{}", sep)?;
+                }
+                write_node(f, n, true)?;
+            }
+            if nested || v.is_empty() {
+                write!(f, ")")?;
+            }
+            Ok(())
+        }
+        FilterNode::Not(_, n) => {
+            write!(f, "not ")?;
+            write_node(f, n, true)
+        }
+        FilterNode::Nullable(ope, field) => match ope {
+            Nullable::IsNull => write!(f, "{} is null", field),
+            Nullable::IsNotNull => write!(f, "{} is not null", field),
+        },
+        FilterNode::Equal(ope, field, v) => {
+            let ope = match ope {
+                Eq::Eq => "=",
+                Eq::NotEq => "!=",
+            };
+            write!(f, "{} {} ", field, ope)?;
+            write_value(f, v)
+        }
+        FilterNode::Cmp(ope, field, v) => {
+            write!(f, "{} {} ", field, ope)?;
+            write_value(f, v)
+        }
+        FilterNode::Between(ope, field, lo, hi) => {
+            write!(f, "{} between ", field)?;
+            let (open, sep, close) = match ope {
+                Between::Between => ("", " and ", ""),
+                Between::GtEqLt => ("[", ", ", ")"),
+                Between::GtLtEq => ("(", ", ", "]"),
+            };
+            write!(f, "{}", open)?;
+            write_value(f, lo)?;
+            write!(f, "{}", sep)?;
+            write_value(f, hi)?;
+            write!(f, "{}", close)
+        }
+        FilterNode::In(ope, field, v) => {
+            match ope {
+                In::In => write!(f, "{} in ", field)?,
+                In::NotIn => write!(f, "{} not in ", field)?,
+            }
+            write_list(f, v)
+        }
+        FilterNode::Like(ope, field, v) => {
+            let v = v.0.replace('\\', "\\\\").replace('%', "\\%");
+            let v = match ope {
+                Like::StartWith => format!("{}%", v),
+                Like::Contains => format!("%{}%", v),
+                Like::EndWith => format!("%{}", v),
+            };
+            write!(f, "{} like ", field)?;
+            write_str(f, &v)
+        }
+    }
+}
+
+impl Display for FilterExpr<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write_node(f, self.0, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_info;
+    use super::super::{field_info_map, Cmp::*, Eq::*, Field, FieldInfo, FieldType as FT};
+    use super::super::{
+        Between as B, FilterLimits, FilterNode, FilterNode::*, In as I, Like::*, ListValue,
+        Logical::*, StrValue, Value,
+    };
+    use super::{ParseErr, ParseErrKind};
+    use std::collections::HashMap;
+
+    fn field(name: &str) -> Field {
+        Field(name.to_string())
+    }
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let info = |name, type_| test_info(name, type_, "");
+        field_info_map(vec![
+            info("id", FT::Id),
+            info("age", FT::Int),
+            info("amount", FT::Num),
+            info("sex", FT::Enum("sex".into())),
+            info("name", FT::Str),
+            info("reg_time", FT::DateTime),
+            info("sub_time", FT::UnixTiemstamp),
+            info("owner_id", FT::Id),
+        ])
+    }
+
+    #[test]
+    fn parse() {
+        let src = "age >= 18 and (sex = 'female' or name like 'Zh%')";
+        let root = FilterNode::parse(src, &infos()).unwrap();
+        #[rustfmt::skip]
+        let res = Logical(And, vec![
+            Cmp(GtEq, field("age"), Value::Int(18)),
+            Logical(Or, vec![
+                Equal(Eq, field("sex"), Value::Str(StrValue("female".into()))),
+                Like(StartWith, field("name"), StrValue("Zh".into())),
+            ]),
+        ]);
+        assert_eq!(root, res);
+        assert_eq!(root.expr().to_string(), src);
+    }
+
+    #[test]
+    fn round_trip() {
+        let infos = &infos();
+        let srcs = [
+            "id in (1, 2, 3) or id not in () or owner_id = id",
+            "not (name is null or name is not null) and not amount < 0.5",
+            "reg_time between '2022-01-01' and '2022-02-01'",
+            "reg_time between [relative(-30, Day, Day), relative(0, Day, Day))",
+            "sub_time between (1, 2] and sub_time <= current_time",
+            "owner_id != current_user_id and name like '%50\\%%' and name = 'it''s'",
+            "((age > 1 and age < 2) or sex in ('a', 'b') or sex not in (1, 2))",
+        ];
+        for src in srcs {
+            let root = FilterNode::parse(src, infos).unwrap();
+            let text = root.expr().to_string();
+            assert_eq!(FilterNode::parse(&text, infos).unwrap(), root, "{}", src);
+        }
+        let root = FilterNode::parse(srcs[5], infos).unwrap();
+        assert_eq!(root.expr().to_string(), srcs[5]);
+    }
+
+    #[test]
+    fn mixed_time_literals() {
+        let infos = &infos();
+        let dt = |s: &str| Value::DateTime(StrValue(s.into()));
+        #[rustfmt::skip]
+        let nodes = [
+            Cmp(Gt, field("reg_time"), Value::UnixTiemstamp(1647273600)),
+            Equal(Eq, field("sub_time"), dt("2022-03-15 00:00:00")),
+            Between(B::GtEqLt, field("reg_time"), Value::UnixTiemstamp(1), dt("2022-01-01")),
+            In(I::In, field("reg_time"), ListValue::UnixTiemstamp(vec![1, 2])),
+            In(I::NotIn, field("sub_time"), ListValue::DateTime(vec![StrValue("2022-01-01".into())])),
+        ];
+        for node in nodes {
+            let text = node.expr().to_string();
+            assert_eq!(FilterNode::parse(&text, infos).unwrap(), node, "{}", text);
+        }
+        assert_eq!(
+            FilterNode::parse("reg_time in (1, '2022-01-01')", infos),
+            Err(ParseErr {
+                pos: 16,
+                kind: ParseErrKind::TypeErr
+            })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let infos = &infos();
+        let err = |pos, kind| Err(ParseErr { pos, kind });
+        use ParseErrKind::*;
+        assert_eq!(FilterNode::parse("age >= 'a'", infos), err(7, TypeErr));
+        assert_eq!(FilterNode::parse("年龄 >= 1", infos), err(0, UnknownField));
+        assert_eq!(
+            FilterNode::parse("age >= 1 and", infos),
+            err(12, UnexpectedEnd)
+        );
+        assert_eq!(
+            FilterNode::parse("name = 'a", infos),
+            err(7, UnterminatedString)
+        );
+        assert_eq!(
+            FilterNode::parse("name like 'a%b'", infos),
+            err(10, InvalidLike)
+        );
+        assert_eq!(
+            FilterNode::parse("age >= 1 )", infos),
+            err(9, UnexpectedToken)
+        );
+        assert_eq!(FilterNode::parse("age # 1", infos), err(4, UnexpectedChar));
+        assert_eq!(FilterNode::parse("age = -1", infos), err(6, InvalidNumber));
+
+        let nested = |n| format!("{}age = 1{}", "not (".repeat(n), ")".repeat(n));
+        assert!(FilterNode::parse(&nested(4), infos).is_ok());
+        assert_eq!(FilterNode::parse(&nested(5), infos), err(20, TooDeep));
+        let limits = FilterLimits {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let parse = |src: &str| FilterNode::parse_with_limits(src, infos, &limits);
+        assert!(parse("not (age = 1)").is_ok());
+        assert_eq!(parse("(((age = 1)))"), err(2, TooDeep));
+        let deep = "(".repeat(100_000) + &")".repeat(100_000);
+        assert_eq!(FilterNode::parse(&deep, infos), err(8, TooDeep));
+    }
+}
@@ -0,0 +1,226 @@
+use super::{DataAccessErr, FilterNode, ListValue, Value};
+use serde::{Deserialize, Serialize};
+
+/// 过滤条件的复杂度限制，防止客户端传入过大的条件拖垮数据库
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterLimits {
+    /// 嵌套层数，叶子节点为 1 层
+    pub max_depth: usize,
+    /// 节点总数
+    pub max_nodes: usize,
+    /// `In` 列表的长度
+    pub max_list_len: usize,
+    /// 字符串值的长度（字符数）
+    pub max_str_len: usize,
+    /// `Like` 条件的个数
+    pub max_like: usize,
+}
+
+impl Default for FilterLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            max_nodes: 200,
+            max_list_len: 1000,
+            max_str_len: 256,
+            max_like: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitKind {
+    Depth,
+    Nodes,
+    ListLen,
+    StrLen,
+    Like,
+}
+
+/// 超过的限制，`actual` 为检查到的值，节点数和层数超过后不再继续统计
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitExceeded {
+    pub kind: LimitKind,
+    pub limit: usize,
+    pub actual: usize,
+}
+
+#[derive(Default)]
+struct Counter {
+    nodes: usize,
+    like: usize,
+}
+
+fn check(limit: usize, actual: usize, kind: LimitKind) -> Result<(), LimitExceeded> {
+    match actual > limit {
+        true => Err(LimitExceeded {
+            kind,
+            limit,
+            actual,
+        }),
+        false => Ok(()),
+    }
+}
+
+fn value_str(v: &Value) -> &str {
+    match v {
+        Value::Str(s) | Value::DateTime(s) => &s.0,
+        _ => "",
+    }
+}
+
+fn max_str_len(v: &ListValue) -> usize {
+    match v {
+        ListValue::Str(v) | ListValue::DateTime(v) => {
+            v.iter().map(|s| s.0.chars().count()).max().unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+impl FilterNode {
+    /// 检查复杂度，在编译和检查字段之前调用
+    pub fn check_limits(&self, limits: &FilterLimits) -> Result<(), DataAccessErr> {
+        let mut counter = Counter::default();
+        self.walk_limits(limits, 1, &mut counter)
+            .map_err(DataAccessErr::TooComplex)
+    }
+
+    /// 超过层数时立即返回，递归深度不会超过 `max_depth`
+    fn walk_limits(
+        &self,
+        limits: &FilterLimits,
+        depth: usize,
+        counter: &mut Counter,
+    ) -> Result<(), LimitExceeded> {
+        check(limits.max_depth, depth, LimitKind::Depth)?;
+        counter.nodes += 1;
+        check(limits.max_nodes, counter.nodes, LimitKind::Nodes)?;
+        let str_len = |s: &str| check(limits.max_str_len, s.chars().count(), LimitKind::StrLen);
+        match self {
+            FilterNode::Logical(_, v) => v
+                .iter()
+                .try_for_each(|n| n.walk_limits(limits, depth + 1, counter)),
+            FilterNode::Not(_, n) => n.walk_limits(limits, depth + 1, counter),
+            FilterNode::Nullable(..) => Ok(()),
+            FilterNode::Equal(_, _, v) | FilterNode::Cmp(_, _, v) => str_len(value_str(v)),
+            FilterNode::Between(_, _, lo, hi) => {
+                str_len(value_str(lo))?;
+                str_len(value_str(hi))
+            }
+            FilterNode::In(_, _, v) => {
+                check(limits.max_list_len, v.len(), LimitKind::ListLen)?;
+                check(limits.max_str_len, max_str_len(v), LimitKind::StrLen)
+            }
+            FilterNode::Like(_, _, v) => {
+                counter.like += 1;
+                check(limits.max_like, counter.like, LimitKind::Like)?;
+                str_len(&v.0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, test_info, Clock, Dialect, EvalContext, FieldType, In};
+    use super::super::{DataAccessErr, Field, FilterNode, ListValue, Logical, Not, StrValue};
+    use super::super::{Like, Sql, SqlCache};
+    use super::{FilterLimits, LimitExceeded, LimitKind};
+    use chrono::{FixedOffset, Utc};
+
+    fn err(node: &FilterNode, limits: &FilterLimits) -> (LimitKind, usize, usize) {
+        match node.check_limits(limits) {
+            Err(DataAccessErr::TooComplex(LimitExceeded {
+                kind,
+                limit,
+                actual,
+            })) => (kind, limit, actual),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    fn like() -> FilterNode {
+        FilterNode::Like(Like::Contains, Field("name".into()), StrValue("a".into()))
+    }
+
+    #[test]
+    fn depth_and_nodes() {
+        let limits = FilterLimits::default();
+        let mut node = like();
+        for _ in 0..7 {
+            node = FilterNode::Not(Not::Not, Box::new(node));
+        }
+        assert_eq!(node.check_limits(&limits), Ok(()));
+        let deep = FilterNode::Not(Not::Not, Box::new(node));
+        assert_eq!(err(&deep, &limits), (LimitKind::Depth, 8, 9));
+
+        let ids = |i| {
+            let v = ListValue::Id(vec![i]);
+            FilterNode::In(In::In, Field("id".into()), v)
+        };
+        let wide = FilterNode::Logical(Logical::Or, (0..300).map(ids).collect());
+        assert_eq!(err(&wide, &limits), (LimitKind::Nodes, 200, 201));
+    }
+
+    #[test]
+    fn values() {
+        let limits = FilterLimits {
+            max_list_len: 3,
+            max_str_len: 4,
+            max_like: 2,
+            ..Default::default()
+        };
+        let list = ListValue::Id(vec![1, 2, 3, 4]);
+        let node = FilterNode::In(In::In, Field("id".into()), list);
+        assert_eq!(err(&node, &limits), (LimitKind::ListLen, 3, 4));
+
+        let s = |s: &str| StrValue(s.into());
+        let list = ListValue::Str(vec![s("a"), s("一二三四五")]);
+        let node = FilterNode::In(In::NotIn, Field("name".into()), list);
+        assert_eq!(err(&node, &limits), (LimitKind::StrLen, 4, 5));
+
+        let node = FilterNode::Logical(Logical::And, vec![like(), like(), like()]);
+        assert_eq!(err(&node, &limits), (LimitKind::Like, 2, 3));
+    }
+
+    #[test]
+    fn entry_points() {
+        let infos = field_info_map(vec![
+            test_info("name", FieldType::Str, " Like ").with_nullable(false)
+        ]);
+        let clock = Clock {
+            now: Utc::now(),
+            tz: FixedOffset::east_opt(0).unwrap(),
+        };
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 0,
+            clock: &clock,
+        };
+        let limits = FilterLimits {
+            max_like: 1,
+            ..Default::default()
+        };
+        let ok = like();
+        let node = FilterNode::Logical(Logical::Or, vec![like(), like()]);
+        let too_complex = |res: Result<_, DataAccessErr>| {
+            assert!(matches!(res, Err(DataAccessErr::TooComplex(_))))
+        };
+        assert!(ok.to_sql(Dialect::MySql, &ctx, &limits).is_ok());
+        too_complex(node.to_sql(Dialect::MySql, &ctx, &limits).map(|_| ()));
+        let mut sql = Sql::new(Dialect::Postgres);
+        too_complex(node.write_sql(&mut sql, &ctx, &limits));
+        let cache = SqlCache::new(10);
+        too_complex(
+            cache
+                .to_sql(&node, Dialect::Sqlite, &ctx, &limits)
+                .map(|_| ()),
+        );
+        assert!(ok.to_mongo(&ctx, &limits).is_ok());
+        too_complex(node.to_mongo(&ctx, &limits).map(|_| ()));
+        assert!(ok.to_es(&ctx, &limits).is_ok());
+        too_complex(node.to_es(&ctx, &limits).map(|_| ()));
+    }
+}
@@ -0,0 +1,341 @@
+use super::{
+    Cmp, DataAccessErr, Decimal, Eq, Field, FieldInfo, FieldType, FilterNode, In, ListValue,
+    Logical, Not, Nullable, StrValue, Value,
+};
+use crate::data_access::{ListNode, LogicalNode, ValueNode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::collections::HashMap;
+
+/// 当前过滤条件的版本号
+pub const FILTER_VERSION: u32 = 1;
+
+/// 带版本号的过滤条件，保存和传输都使用这个格式
+///
+/// `{ "version": 1, "filter": ["And", [...]] }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionedFilter {
+    pub version: u32,
+    pub filter: FilterNode,
+}
+
+/// 兼容的 json 格式
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyFilter {
+    Versioned(VersionedFilter),
+    /// 没有版本号的 [`FilterNode`]
+    Untagged(FilterNode),
+    /// [`crate::data_access::LogicalNode`]
+    Legacy(LogicalNode),
+}
+
+impl VersionedFilter {
+    pub fn new(filter: FilterNode) -> Self {
+        Self {
+            version: FILTER_VERSION,
+            filter,
+        }
+    }
+
+    /// 读取保存的过滤条件，兼容旧的 `LogicalNode`、没有版本号的 `FilterNode` 和旧的 `ListValue`
+    pub fn from_json(json: &str) -> Result<Self, DataAccessErr> {
+        let json = serde_json::from_str(json).map_err(|_| DataAccessErr::InvalidFormat)?;
+        Self::from_value(json)
+    }
+
+    pub fn from_value(json: Json) -> Result<Self, DataAccessErr> {
+        let any = serde_json::from_value(json).map_err(|_| DataAccessErr::InvalidFormat)?;
+        Self::from_any(any)
+    }
+
+    /// 同 [`VersionedFilter::from_value`]，旧的 `LogicalNode` 只有 Int、Num、Str 等值，
+    /// 再按 `infos` 中的字段类型转换，例如 Id 字段的 Int 转为 Id，转换后可以通过
+    /// [`FilterNode::check`]；新格式的值已经带类型，不做转换
+    pub fn from_value_with(
+        json: Json,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<Self, DataAccessErr> {
+        let any = serde_json::from_value(json).map_err(|_| DataAccessErr::InvalidFormat)?;
+        match any {
+            AnyFilter::Legacy(node) => Ok(Self::new(fit_types(node.try_into()?, infos))),
+            any => Self::from_any(any),
+        }
+    }
+
+    fn from_any(any: AnyFilter) -> Result<Self, DataAccessErr> {
+        match any {
+            AnyFilter::Versioned(v) if v.version == FILTER_VERSION => Ok(v),
+            AnyFilter::Versioned(_) => Err(DataAccessErr::InvalidFormat),
+            AnyFilter::Untagged(filter) => Ok(Self::new(filter)),
+            AnyFilter::Legacy(node) => Ok(Self::new(node.try_into()?)),
+        }
+    }
+}
+
+impl TryFrom<LogicalNode> for FilterNode {
+    type Error = DataAccessErr;
+
+    fn try_from(node: LogicalNode) -> Result<Self, Self::Error> {
+        use LogicalNode as L;
+        let field = |f: crate::data_access::Field| Field(f.0);
+        let logical = |ope, v: Vec<LogicalNode>| {
+            let v = v.into_iter().map(FilterNode::try_from);
+            Ok(FilterNode::Logical(ope, v.collect::<Result<_, _>>()?))
+        };
+        Ok(match node {
+            L::And(v) => return logical(Logical::And, v),
+            L::Or(v) => return logical(Logical::Or, v),
+            L::Not(n) => FilterNode::Not(Not::Not, Box::new((*n).try_into()?)),
+            L::IsNull(f) => FilterNode::Nullable(Nullable::IsNull, field(f)),
+            L::IsNotNull(f) => FilterNode::Nullable(Nullable::IsNotNull, field(f)),
+            L::Eq(f, v) => FilterNode::Equal(Eq::Eq, field(f), v.try_into()?),
+            L::NotEq(f, v) => FilterNode::Equal(Eq::NotEq, field(f), v.try_into()?),
+            L::Gt(f, v) => FilterNode::Cmp(Cmp::Gt, field(f), v.try_into()?),
+            L::Gte(f, v) => FilterNode::Cmp(Cmp::GtEq, field(f), v.try_into()?),
+            L::Lt(f, v) => FilterNode::Cmp(Cmp::Lt, field(f), v.try_into()?),
+            L::Lte(f, v) => FilterNode::Cmp(Cmp::LtEq, field(f), v.try_into()?),
+            L::In(f, v) => FilterNode::In(In::In, field(f), v.try_into()?),
+            L::NotIn(f, v) => FilterNode::In(In::NotIn, field(f), v.try_into()?),
+        })
+    }
+}
+
+/// 旧格式的整数是 i64，负数无法转换
+fn to_u64(v: i64) -> Result<u64, DataAccessErr> {
+    u64::try_from(v).map_err(|_| DataAccessErr::TypeErr)
+}
+
+impl TryFrom<ValueNode> for Value {
+    type Error = DataAccessErr;
+
+    fn try_from(v: ValueNode) -> Result<Self, Self::Error> {
+        Ok(match v {
+            ValueNode::Str(v) => Value::Str(StrValue(v)),
+            ValueNode::Int(v) => Value::Int(to_u64(v)?),
+            ValueNode::Num(v) => Value::Num(v),
+            ValueNode::DateTime(v) => Value::DateTime(StrValue(v)),
+            ValueNode::UnixTimestamp(v) => Value::UnixTiemstamp(v),
+            ValueNode::Field(v) => Value::Field(Field(v)),
+            ValueNode::CurrentUserId => Value::CurrentUserId,
+            ValueNode::CurrentTime => Value::CurrentTime,
+            ValueNode::CurrentDate => Value::CurrentDate,
+        })
+    }
+}
+
+impl TryFrom<ListNode> for ListValue {
+    type Error = DataAccessErr;
+
+    fn try_from(v: ListNode) -> Result<Self, Self::Error> {
+        Ok(match v {
+            ListNode::StrList(v) => ListValue::Str(v.into_iter().map(StrValue).collect()),
+            ListNode::IntList(v) => {
+                ListValue::Int(v.into_iter().map(to_u64).collect::<Result<_, _>>()?)
+            }
+            ListNode::NumList(v) => ListValue::Num(v),
+        })
+    }
+}
+
+/// 按字段类型转换旧格式迁移来的值，字段不存在时不变，留给 [`FilterNode::check`] 报错
+fn fit_types(node: FilterNode, infos: &HashMap<String, FieldInfo>) -> FilterNode {
+    let ft = |field: &Field| infos.get(&field.0).map(|info| &info.type_);
+    match node {
+        FilterNode::Logical(ope, v) => {
+            FilterNode::Logical(ope, v.into_iter().map(|n| fit_types(n, infos)).collect())
+        }
+        FilterNode::Not(ope, n) => FilterNode::Not(ope, Box::new(fit_types(*n, infos))),
+        FilterNode::Equal(ope, f, v) => {
+            let v = fit_value(v, ft(&f));
+            FilterNode::Equal(ope, f, v)
+        }
+        FilterNode::Cmp(ope, f, v) => {
+            let v = fit_value(v, ft(&f));
+            FilterNode::Cmp(ope, f, v)
+        }
+        FilterNode::In(ope, f, v) => {
+            let v = fit_list(v, ft(&f));
+            FilterNode::In(ope, f, v)
+        }
+        node => node,
+    }
+}
+
+fn fit_value(v: Value, ft: Option<&FieldType>) -> Value {
+    use FieldType as FT;
+    match (v, ft) {
+        (Value::Int(v), Some(FT::Id)) => Value::Id(v),
+        (Value::Int(v), Some(FT::Num)) => Value::Num(v as f64),
+        (Value::Int(v), Some(FT::Decimal { .. })) => Value::Decimal(Decimal::from(v)),
+        (Value::Int(v), Some(FT::DateTime | FT::UnixTiemstamp)) => Value::UnixTiemstamp(v),
+        (Value::Num(v), Some(FT::Decimal { .. })) => match Decimal::from_f64(v) {
+            Some(d) => Value::Decimal(d),
+            None => Value::Num(v),
+        },
+        (Value::Str(v), Some(FT::DateTime | FT::UnixTiemstamp)) => Value::DateTime(v),
+        (v, _) => v,
+    }
+}
+
+/// 旧格式只有 `IntList`、`NumList`、`StrList`，Enum 字段的 `StrList` 和 `IntList` 不用转换
+fn fit_list(v: ListValue, ft: Option<&FieldType>) -> ListValue {
+    use FieldType as FT;
+    match (v, ft) {
+        (ListValue::Int(v), Some(FT::Id)) => ListValue::Id(v),
+        (ListValue::Int(v), Some(FT::Num)) => {
+            ListValue::Num(v.into_iter().map(|v| v as f64).collect())
+        }
+        (ListValue::Int(v), Some(FT::Decimal { .. })) => {
+            ListValue::Decimal(v.into_iter().map(Decimal::from).collect())
+        }
+        (ListValue::Num(v), Some(FT::Decimal { .. })) => {
+            match v.iter().map(|v| Decimal::from_f64(*v)).collect() {
+                Some(d) => ListValue::Decimal(d),
+                None => ListValue::Num(v),
+            }
+        }
+        (ListValue::Int(v), Some(FT::DateTime | FT::UnixTiemstamp)) => ListValue::UnixTiemstamp(v),
+        (ListValue::Str(v), Some(FT::DateTime | FT::UnixTiemstamp)) => ListValue::DateTime(v),
+        (v, _) => v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, test_info, FieldType as FT};
+    use super::super::{
+        Cmp::*,
+        Eq::*,
+        Field,
+        FilterNode::{self, *},
+        Logical::*,
+    };
+    use super::super::{In as I, ListValue, Not::Not as NotOpe, StrValue, Value};
+    use super::{DataAccessErr, VersionedFilter};
+
+    fn field(name: &str) -> Field {
+        Field(name.to_string())
+    }
+
+    #[test]
+    fn legacy_logical_node() {
+        let json = r#"{"and":[
+            {"eq":["id",{"int":123}]},
+            {"gte":["age",{"num":18.0}]},
+            {"not":{"in":["sex",{"strList":["male"]}]}},
+            {"lte":["time","currentTime"]}
+        ]}"#;
+        #[rustfmt::skip]
+        let filter = Logical(And, vec![
+            Equal(Eq, field("id"), Value::Int(123)),
+            Cmp(GtEq, field("age"), Value::Num(18.0)),
+            Not(NotOpe, Box::new(In(I::In, field("sex"), ListValue::Str(vec![StrValue("male".into())])))),
+            Cmp(LtEq, field("time"), Value::CurrentTime),
+        ]);
+        assert_eq!(
+            VersionedFilter::from_json(json),
+            Ok(VersionedFilter::new(filter))
+        );
+        let json = r#"{"eq":["id",{"int":-1}]}"#;
+        assert_eq!(
+            VersionedFilter::from_json(json),
+            Err(DataAccessErr::TypeErr)
+        );
+    }
+
+    #[test]
+    fn legacy_with_infos() {
+        let info = |name, type_| test_info(name, type_, " Equal Cmp In ").with_nullable(false);
+        let infos = field_info_map(vec![
+            info("id", FT::Id),
+            info("sex", FT::Enum("sex".into())),
+            info("amount", FT::Decimal { scale: 2 }),
+            info("reg_time", FT::DateTime),
+        ]);
+        let json = serde_json::json!({"and":[
+            {"eq":["id",{"int":123}]},
+            {"in":["id",{"intList":[1,2]}]},
+            {"in":["sex",{"strList":["male"]}]},
+            {"gte":["amount",{"num":0.1}]},
+            {"lt":["reg_time",{"str":"2022-01-01"}]}
+        ]});
+        // 不按字段类型转换时 Int 不能和 Id 字段比较
+        let v = VersionedFilter::from_value(json.clone()).unwrap();
+        assert_eq!(v.filter.check(&infos), Err(DataAccessErr::TypeErr));
+
+        let v = VersionedFilter::from_value_with(json, &infos).unwrap();
+        assert_eq!(v.filter.check(&infos), Ok(()));
+        #[rustfmt::skip]
+        let filter = Logical(And, vec![
+            Equal(Eq, field("id"), Value::Id(123)),
+            In(I::In, field("id"), ListValue::Id(vec![1, 2])),
+            In(I::In, field("sex"), ListValue::Str(vec![StrValue("male".into())])),
+            Cmp(GtEq, field("amount"), Value::Decimal("0.1".parse().unwrap())),
+            Cmp(Lt, field("reg_time"), Value::DateTime(StrValue("2022-01-01".into()))),
+        ]);
+        assert_eq!(v, VersionedFilter::new(filter));
+
+        // 新格式的值已经带类型，不转换
+        let json = serde_json::json!(["Eq", "id", {"type": "Int", "value": 1}]);
+        let v = VersionedFilter::from_value_with(json, &infos).unwrap();
+        assert_eq!(v.filter.check(&infos), Err(DataAccessErr::TypeErr));
+    }
+
+    #[test]
+    fn legacy_list_value() {
+        let json = r#"{"version":1,"filter":["And",[
+            ["In","id",{"Id":[1,2]}],
+            ["Not",["NotIn","name",{"type":"Str","value":["a"]}]]
+        ]]}"#;
+        #[rustfmt::skip]
+        let filter = Logical(And, vec![
+            In(I::In, field("id"), ListValue::Id(vec![1, 2])),
+            Not(NotOpe, Box::new(In(I::NotIn, field("name"), ListValue::Str(vec![StrValue("a".into())])))),
+        ]);
+        let v = VersionedFilter::from_json(json).unwrap();
+        assert_eq!(v, VersionedFilter::new(filter));
+        let json = serde_json::to_string(&v).unwrap();
+        assert!(json.contains(r#"["In","id",{"type":"Id","value":[1,2]}]"#));
+
+        // 不经过 VersionedFilter，直接反序列化 FilterNode 也兼容两种格式
+        let id = In(I::In, field("id"), ListValue::Id(vec![1, 2]));
+        for json in [
+            r#"["In","id",{"Id":[1,2]}]"#,
+            r#"["In","id",{"type":"Id","value":[1,2]}]"#,
+        ] {
+            assert_eq!(serde_json::from_str::<FilterNode>(json).unwrap(), id);
+        }
+
+        let json = r#"["In","id",{"Id":[1],"Int":[2]}]"#;
+        assert_eq!(
+            VersionedFilter::from_json(json),
+            Err(DataAccessErr::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn untagged_and_versioned() {
+        let json = r#"["Eq","name",{"type":"Str","value":"AAAA"}]"#;
+        let filter = Equal(Eq, field("name"), Value::Str(StrValue("AAAA".into())));
+        let v = VersionedFilter::from_json(json).unwrap();
+        assert_eq!(v, VersionedFilter::new(filter));
+
+        let json = serde_json::to_string(&v).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"filter":["Eq","name",{"type":"Str","value":"AAAA"}]}"#
+        );
+        assert_eq!(VersionedFilter::from_json(&json), Ok(v));
+
+        let json = r#"{"version":99,"filter":["Eq","name",{"type":"Str","value":"AAAA"}]}"#;
+        assert_eq!(
+            VersionedFilter::from_json(json),
+            Err(DataAccessErr::InvalidFormat)
+        );
+        assert_eq!(
+            VersionedFilter::from_json("{}"),
+            Err(DataAccessErr::InvalidFormat)
+        );
+    }
+}
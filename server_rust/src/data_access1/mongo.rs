@@ -0,0 +1,355 @@
+use super::time::{parse_datetime, resolve_list, resolve_now};
+use super::{
+    Cmp, DataAccessErr, Eq, EvalContext, Field, FieldType, FilterLimits, FilterNode, In, Like,
+    ListValue, Logical, Nullable, StrValue, Value,
+};
+use serde_json::{json, Map, Value as Json};
+
+fn cmp_op(ope: &Cmp) -> &'static str {
+    match ope {
+        Cmp::Gt => "$gt",
+        Cmp::GtEq => "$gte",
+        Cmp::Lt => "$lt",
+        Cmp::LtEq => "$lte",
+    }
+}
+
+/// 转义正则的元字符，Like 只做字面量匹配
+fn regex_escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+fn like_regex(ope: &Like, v: &StrValue) -> String {
+    let s = regex_escape(&v.0);
+    match ope {
+        Like::StartWith => format!("^{}", s),
+        Like::Contains => s,
+        Like::EndWith => format!("{}$", s),
+    }
+}
+
+fn list(v: &ListValue) -> Vec<Json> {
+    match v {
+        ListValue::Id(v) | ListValue::Int(v) | ListValue::UnixTiemstamp(v) => {
+            v.iter().map(|v| json!(v)).collect()
+        }
+        ListValue::Num(v) => v.iter().map(|v| json!(v)).collect(),
+        ListValue::Decimal(v) => v.iter().map(|v| json!(v)).collect(),
+        ListValue::Str(v) | ListValue::DateTime(v) => v.iter().map(|v| json!(v.0)).collect(),
+    }
+}
+
+/// 单个字段的条件 `{ field: { op: value } }`
+fn field_doc(field: &Field, ops: Map<String, Json>) -> Json {
+    json!({ field.0.clone(): ops })
+}
+
+/// 字段不为 NULL，`$ne: null` 同时排除了不存在的字段
+fn not_null(field: &Field) -> Json {
+    json!({ field.0.clone(): { "$ne": null } })
+}
+
+impl EvalContext<'_> {
+    /// 值对应的 json，时间按 `clock` 解析为具体时间，定点小数为字符串
+    pub(super) fn json_value(&self, v: &Value, ft: &FieldType) -> Result<Json, DataAccessErr> {
+        v.check_field_type(ft, self.infos)?;
+        Ok(match resolve_now(v, ft, self.clock)? {
+            Value::Id(v) | Value::Int(v) | Value::UnixTiemstamp(v) => json!(v),
+            Value::Num(v) => json!(v),
+            Value::Decimal(v) => json!(v),
+            Value::Str(v) | Value::DateTime(v) => json!(v.0),
+            Value::CurrentUserId => json!(self.user_id),
+            Value::Field(f) => json!(format!("${}", f.0)),
+            Value::CurrentDate | Value::CurrentTime | Value::RelativeTime(_) => {
+                return Err(DataAccessErr::TypeErr)
+            }
+        })
+    }
+
+    /// 字面量转为扩展 json，定点小数为 Decimal128 才能按数值比较，
+    /// `DateTime` 字段为带时区的 BSON 日期，按时刻比较
+    fn ejson(&self, ft: &FieldType, v: Json) -> Result<Json, DataAccessErr> {
+        Ok(match (ft, v) {
+            (FieldType::Decimal { .. }, Json::String(s)) => json!({ "$numberDecimal": s }),
+            (FieldType::DateTime, Json::String(s)) => {
+                let time = parse_datetime(&s, self.clock.tz)?;
+                json!({ "$date": time.to_rfc3339() })
+            }
+            (_, v) => v,
+        })
+    }
+
+    /// 列表对应的 json，时间同 [`EvalContext::json_value`]
+    pub(super) fn json_list(
+        &self,
+        field: &Field,
+        v: &ListValue,
+    ) -> Result<Vec<Json>, DataAccessErr> {
+        let ft = &self.infos[&field.0].type_;
+        Ok(list(resolve_list(v, ft, self.clock)?.as_ref()))
+    }
+
+    /// 比较条件，引用字段时使用 `$expr`，并要求两边都不为 NULL，与 sql 一致
+    fn mongo_cmp(
+        &self,
+        field: &Field,
+        conds: &[(&'static str, &Value)],
+    ) -> Result<Json, DataAccessErr> {
+        let ft = &self
+            .infos
+            .get(&field.0)
+            .ok_or(DataAccessErr::UnknownField)?
+            .type_;
+        let mut ops = Map::new();
+        let mut exprs = vec![];
+        let mut guards = vec![];
+        for (op, v) in conds {
+            let value = self.json_value(v, ft)?;
+            match v {
+                Value::Field(f) => {
+                    guards.push(not_null(f));
+                    exprs.push(json!({ "$expr": { *op: [format!("${}", field.0), value] } }));
+                }
+                _ => {
+                    ops.insert(op.to_string(), self.ejson(ft, value)?);
+                }
+            }
+        }
+        if exprs.is_empty() {
+            return Ok(field_doc(field, ops));
+        }
+        let mut and = vec![not_null(field)];
+        and.extend(guards);
+        if !ops.is_empty() {
+            and.push(field_doc(field, ops));
+        }
+        and.extend(exprs);
+        Ok(json!({ "$and": and }))
+    }
+}
+
+impl FilterNode {
+    /// 编译为 MongoDB 的查询文档，先检查 `limits`，再检查同 [`FilterNode::check`]
+    ///
+    /// 先通过 [`FilterNode::push_not`] 下推 Not，比较、`$nin` 都排除 NULL，
+    /// 结果与 sql 的三值逻辑一致
+    pub fn to_mongo(
+        &self,
+        ctx: &EvalContext,
+        limits: &FilterLimits,
+    ) -> Result<Json, DataAccessErr> {
+        self.check_limits(limits)?;
+        self.check(ctx.infos)?;
+        self.clone().push_not().mongo(ctx)
+    }
+
+    fn mongo(&self, ctx: &EvalContext) -> Result<Json, DataAccessErr> {
+        Ok(match self {
+            FilterNode::Logical(ope, v) => {
+                let v = v
+                    .iter()
+                    .map(|n| n.mongo(ctx))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match (ope, v.is_empty()) {
+                    (Logical::And, true) => json!({}),
+                    (Logical::Or, true) => json!({ "$expr": false }),
+                    (Logical::And, false) => json!({ "$and": v }),
+                    (Logical::Or, false) => json!({ "$or": v }),
+                }
+            }
+            // push_not 之后只有 Not Like
+            FilterNode::Not(_, n) => match n.as_ref() {
+                FilterNode::Like(ope, field, v) => json!({
+                    field.0.clone(): { "$ne": null, "$not": { "$regex": like_regex(ope, v) } }
+                }),
+                n => json!({ "$nor": [n.mongo(ctx)?] }),
+            },
+            FilterNode::Nullable(ope, field) => match ope {
+                // `null` 同时匹配值为 null 和不存在的字段
+                Nullable::IsNull => json!({ field.0.clone(): null }),
+                Nullable::IsNotNull => json!({ field.0.clone(): { "$exists": true, "$ne": null } }),
+            },
+            FilterNode::Equal(ope, field, v) => match (ope, v) {
+                (Eq::Eq, v) => ctx.mongo_cmp(field, &[("$eq", v)])?,
+                (Eq::NotEq, Value::Field(_)) => ctx.mongo_cmp(field, &[("$ne", v)])?,
+                (Eq::NotEq, v) => {
+                    let ft = &ctx.infos[&field.0].type_;
+                    let v = ctx.ejson(ft, ctx.json_value(v, ft)?)?;
+                    json!({ field.0.clone(): { "$nin": [v, null] } })
+                }
+            },
+            FilterNode::Cmp(ope, field, v) => ctx.mongo_cmp(field, &[(cmp_op(ope), v)])?,
+            FilterNode::Between(ope, field, lo, hi) => {
+                let (lo_ope, hi_ope) = ope.bounds();
+                ctx.mongo_cmp(field, &[(cmp_op(&lo_ope), lo), (cmp_op(&hi_ope), hi)])?
+            }
+            FilterNode::In(ope, _, v) if v.is_empty() => match ope {
+                In::In => json!({ "$expr": false }),
+                In::NotIn => json!({}),
+            },
+            FilterNode::In(ope, field, v) => {
+                let ft = &ctx.infos[&field.0].type_;
+                let v = ctx.json_list(field, v)?.into_iter();
+                let mut v = v.map(|v| ctx.ejson(ft, v)).collect::<Result<Vec<_>, _>>()?;
+                match ope {
+                    In::In => json!({ field.0.clone(): { "$in": v } }),
+                    In::NotIn => {
+                        v.push(Json::Null);
+                        json!({ field.0.clone(): { "$nin": v } })
+                    }
+                }
+            }
+            FilterNode::Like(ope, field, v) => {
+                json!({ field.0.clone(): { "$regex": like_regex(ope, v) } })
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, test_info, Clock, DataAccessErr, EvalContext, FieldInfo};
+    use super::super::{FieldType as FT, FilterLimits, FilterNode};
+    use chrono::{FixedOffset, TimeZone, Utc};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        field_info_map(vec![
+            test_info("age", FT::Int, " Equal Cmp In "),
+            test_info("name", FT::Str, " Equal Like "),
+            test_info("owner_id", FT::Id, " Equal "),
+            test_info("created_by", FT::Id, " Equal "),
+            test_info("reg_time", FT::DateTime, " Cmp "),
+            test_info("login_time", FT::DateTime, " Equal Cmp In "),
+            test_info("amount", FT::Decimal { scale: 2 }, " Equal Cmp In "),
+        ])
+    }
+
+    fn mongo(src: &str) -> Result<serde_json::Value, DataAccessErr> {
+        let infos = infos();
+        let clock = Clock {
+            now: Utc.with_ymd_and_hms(2022, 3, 15, 18, 30, 0).unwrap(),
+            tz: FixedOffset::east_opt(8 * 3600).unwrap(),
+        };
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 7,
+            clock: &clock,
+        };
+        FilterNode::parse(src, &infos)
+            .unwrap()
+            .to_mongo(&ctx, &FilterLimits::default())
+    }
+
+    #[test]
+    fn to_mongo() {
+        assert_eq!(
+            mongo("age >= 18 and (name like '张%' or name is null)").unwrap(),
+            json!({ "$and": [
+                { "age": { "$gte": 18 } },
+                { "$or": [{ "name": { "$regex": "^张" } }, { "name": null }] },
+            ] })
+        );
+        assert_eq!(
+            mongo("age in (1, 2) or owner_id = current_user_id").unwrap(),
+            json!({ "$or": [{ "age": { "$in": [1, 2] } }, { "owner_id": { "$eq": 7 } }] })
+        );
+        assert_eq!(
+            mongo("reg_time between [relative(-1, Day, Day), current_date)").unwrap(),
+            json!({ "reg_time": {
+                "$gte": { "$date": "2022-03-15T00:00:00+08:00" },
+                "$lt": { "$date": "2022-03-16T00:00:00+08:00" },
+            } })
+        );
+        assert_eq!(
+            mongo("name like '%a.b%'").unwrap(),
+            json!({ "name": { "$regex": "a\\.b" } })
+        );
+    }
+
+    #[test]
+    fn decimal() {
+        let dec = |s: &str| json!({ "$numberDecimal": s });
+        assert_eq!(
+            mongo("amount >= 19.99 and amount not in (0.1, 18)").unwrap(),
+            json!({ "$and": [
+                { "amount": { "$gte": dec("19.99") } },
+                { "amount": { "$nin": [dec("0.1"), dec("18"), null] } },
+            ] })
+        );
+    }
+
+    #[test]
+    fn datetime() {
+        let date = |s: &str| json!({ "$date": s });
+        // 不同格式的时间都转为带时区的同一种格式
+        let src = "login_time <> '2022-03-15T18:30:00Z' \
+            and login_time in ('2022-03-16', '2022-03-15T16:00:00Z')";
+        assert_eq!(
+            mongo(src).unwrap(),
+            json!({ "$and": [
+                { "login_time": { "$nin": [date("2022-03-16T02:30:00+08:00"), null] } },
+                { "login_time": { "$in": [
+                    date("2022-03-16T00:00:00+08:00"),
+                    date("2022-03-16T00:00:00+08:00"),
+                ] } },
+            ] })
+        );
+        assert_eq!(
+            mongo("login_time >= current_time").unwrap(),
+            json!({ "login_time": { "$gte": date("2022-03-16T02:30:00+08:00") } })
+        );
+    }
+
+    #[test]
+    fn not() {
+        assert_eq!(
+            mongo("not (age > 18 or name is not null)").unwrap(),
+            json!({ "$and": [{ "age": { "$lte": 18 } }, { "name": null }] })
+        );
+        assert_eq!(
+            mongo("not age in (1) and name <> 'a'").unwrap(),
+            json!({ "$and": [
+                { "age": { "$nin": [1, null] } },
+                { "name": { "$nin": ["a", null] } },
+            ] })
+        );
+        assert_eq!(
+            mongo("age in () or not age in ()").unwrap(),
+            json!({ "$or": [{ "$expr": false }, {}] })
+        );
+        assert_eq!(
+            mongo("not name like '%a'").unwrap(),
+            json!({ "name": { "$ne": null, "$not": { "$regex": "a$" } } })
+        );
+    }
+
+    #[test]
+    fn field_ref() {
+        assert_eq!(
+            mongo("owner_id = created_by").unwrap(),
+            json!({ "$and": [
+                { "owner_id": { "$ne": null } },
+                { "created_by": { "$ne": null } },
+                { "$expr": { "$eq": ["$owner_id", "$created_by"] } },
+            ] })
+        );
+    }
+
+    #[test]
+    fn check() {
+        assert_eq!(
+            mongo("reg_time = '2022-01-01'"),
+            Err(DataAccessErr::InvalidOperation)
+        );
+        assert_eq!(mongo("owner_id > 1"), Err(DataAccessErr::InvalidOperation));
+    }
+}
@@ -0,0 +1,232 @@
+use super::cursor::keyset;
+use super::sort::SortSpec;
+use super::sql::{uint, Dialect, Sql};
+use super::{
+    field_info_map, Clock, DataAccessErr, EvalContext, FieldInfo, FilterLimits, FilterNode, Value,
+};
+use serde::{Deserialize, Serialize};
+
+/// 分页方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Page {
+    Offset {
+        limit: u64,
+        offset: u64,
+    },
+    /// 从上一页最后一行之后开始，`after` 和排序字段一一对应，
+    /// 排序字段需要能唯一确定一行（例如最后加上 id），且不能为 NULL
+    Keyset {
+        limit: u64,
+        after: Vec<Value>,
+    },
+}
+
+/// 列表查询：字段、过滤条件、排序和分页，编译为完整的 SELECT
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListQuery {
+    pub table: String,
+    /// 有权限的字段，同时是查询的列和过滤、排序可以使用的字段
+    pub fields: Vec<FieldInfo>,
+    pub filter: Option<FilterNode>,
+    pub sort: SortSpec,
+    pub page: Page,
+    /// 过滤条件的复杂度限制
+    pub limits: FilterLimits,
+    /// 见 [`Sql::array_min_len`]
+    pub array_min_len: Option<usize>,
+}
+
+impl ListQuery {
+    /// 游标分页时排序字段不能为 NULL，否则 NULL 所在的行无法比较
+    fn check_keyset(&self, after: &[Value]) -> Result<(), DataAccessErr> {
+        if self.sort.is_empty() || after.len() != self.sort.0.len() {
+            return Err(DataAccessErr::InvalidFormat);
+        }
+        for item in self.sort.0.iter() {
+            let info = self.fields.iter().find(|f| f.name == item.field);
+            if info.is_some_and(|f| f.nullable) {
+                return Err(DataAccessErr::InvalidOperation);
+            }
+        }
+        Ok(())
+    }
+
+    /// 编译为参数化的 sql，过滤条件检查同 [`FilterNode::check`]
+    pub fn to_sql(
+        &self,
+        dialect: Dialect,
+        user_id: u64,
+        clock: &Clock,
+    ) -> Result<Sql, DataAccessErr> {
+        if self.fields.is_empty() {
+            return Err(DataAccessErr::InvalidOperation);
+        }
+        let infos = field_info_map(self.fields.clone());
+        self.sort.check(&infos)?;
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id,
+            clock,
+        };
+        let mut conds = vec![];
+        if let Some(filter) = &self.filter {
+            filter.check_limits(&self.limits)?;
+            filter.check(&infos)?;
+            conds.push(filter.clone());
+        }
+        let limit = match &self.page {
+            Page::Offset { limit, .. } => limit,
+            Page::Keyset { limit, after } => {
+                self.check_keyset(after)?;
+                conds.push(keyset(&self.sort, after));
+                limit
+            }
+        };
+
+        let mut sql = Sql::new(dialect);
+        sql.array_min_len = self.array_min_len;
+        sql.push("SELECT ");
+        for (i, info) in self.fields.iter().enumerate() {
+            if i > 0 {
+                sql.push(", ");
+            }
+            sql.ident(&info.name);
+        }
+        sql.push(" FROM ");
+        sql.ident(&self.table);
+        for (i, cond) in conds.iter().enumerate() {
+            sql.push(if i == 0 { " WHERE " } else { " AND " });
+            sql.node(cond, &ctx)?;
+        }
+        self.sort.write(&mut sql, &infos);
+        sql.push(" LIMIT ");
+        sql.bind(uint(*limit)?);
+        if let Page::Offset { offset, .. } = self.page {
+            sql.push(" OFFSET ");
+            sql.bind(uint(offset)?);
+        }
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, Bind::*, Clock, DataAccessErr, Dialect, FieldInfo};
+    use super::super::{test_info, FieldType as FT, FilterLimits, FilterNode, StrValue, Value};
+    use super::super::{SortItem, SortSpec};
+    use super::{ListQuery, Page};
+    use chrono::FixedOffset;
+
+    fn fields() -> Vec<FieldInfo> {
+        let info = |name, type_, nullable, sortable| {
+            let info = test_info(name, type_, " Equal Cmp In ");
+            info.with_nullable(nullable).with_sortable(sortable)
+        };
+        vec![
+            info("id", FT::Id, false, true),
+            info("name", FT::Str, true, false),
+            info("reg_time", FT::DateTime, false, true),
+        ]
+    }
+
+    fn query(sort: &[(&str, bool)], page: Page) -> ListQuery {
+        let infos = field_info_map(fields());
+        ListQuery {
+            table: "app.user".into(),
+            fields: fields(),
+            filter: Some(FilterNode::parse("name = 'a'", &infos).unwrap()),
+            sort: SortSpec(
+                sort.iter()
+                    .map(|(field, desc)| match desc {
+                        true => SortItem::desc(field),
+                        false => SortItem::asc(field),
+                    })
+                    .collect(),
+            ),
+            page,
+            limits: FilterLimits::default(),
+            array_min_len: None,
+        }
+    }
+
+    fn clock() -> Clock {
+        Clock::system(FixedOffset::east_opt(0).unwrap())
+    }
+
+    #[test]
+    fn offset() {
+        let page = Page::Offset {
+            limit: 20,
+            offset: 40,
+        };
+        let sql = query(&[("reg_time", true)], page)
+            .to_sql(Dialect::Postgres, 1, &clock())
+            .unwrap();
+        assert_eq!(
+            sql.sql,
+            r#"SELECT "id", "name", "reg_time" FROM "app"."user" WHERE "name" = $1 ORDER BY "reg_time" DESC LIMIT $2 OFFSET $3"#
+        );
+        assert_eq!(sql.binds[..], [Str("a".into()), Int(20), Int(40)]);
+    }
+
+    #[test]
+    fn array() {
+        let page = Page::Offset {
+            limit: 20,
+            offset: 0,
+        };
+        let mut q = query(&[], page);
+        q.filter = Some(FilterNode::parse("id in (1, 2, 3)", &field_info_map(fields())).unwrap());
+        q.array_min_len = Some(3);
+        let sql = q.to_sql(Dialect::Postgres, 1, &clock()).unwrap();
+        assert_eq!(
+            sql.sql,
+            r#"SELECT "id", "name", "reg_time" FROM "app"."user" WHERE "id" = ANY($1) LIMIT $2 OFFSET $3"#
+        );
+        assert_eq!(sql.binds[0], IntArray(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn keyset() {
+        let time = Value::DateTime(StrValue("2022-01-01 00:00:00".into()));
+        let page = Page::Keyset {
+            limit: 20,
+            after: vec![time, Value::Id(9)],
+        };
+        let sql = query(&[("reg_time", true), ("id", false)], page)
+            .to_sql(Dialect::MySql, 1, &clock())
+            .unwrap();
+        assert_eq!(
+            sql.sql,
+            "SELECT `id`, `name`, `reg_time` FROM `app`.`user` WHERE `name` = ? \
+             AND (`reg_time` < ? OR (`reg_time` = ? AND `id` > ?)) \
+             ORDER BY `reg_time` DESC, `id` ASC LIMIT ?"
+        );
+        assert_eq!(sql.binds.len(), 5);
+    }
+
+    #[test]
+    fn check() {
+        let page = || Page::Offset {
+            limit: 20,
+            offset: 0,
+        };
+        let err = |q: ListQuery| q.to_sql(Dialect::Sqlite, 1, &clock()).unwrap_err();
+        assert_eq!(
+            err(query(&[("name", false)], page())),
+            DataAccessErr::InvalidOperation
+        );
+        assert_eq!(
+            err(query(&[("age", false)], page())),
+            DataAccessErr::UnknownField
+        );
+        let page = Page::Keyset {
+            limit: 20,
+            after: vec![],
+        };
+        assert_eq!(
+            err(query(&[("id", false)], page)),
+            DataAccessErr::InvalidFormat
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use super::sql::{Dialect, Sql};
+use super::{
+    field_info_map, Clock, DataAccessErr, EvalContext, FieldInfo, FieldType, FilterLimits,
+    FilterNode,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AggFn {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+/// 聚合列，`field` 为空时只能是 `Count`，即 `COUNT(*)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Aggregate {
+    pub func: AggFn,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// 结果的列名，HAVING 中通过它引用聚合的结果
+    pub alias: String,
+}
+
+/// 报表查询：按 `group_by` 分组，计算 `aggregates`，再用 `having` 过滤分组
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportQuery {
+    pub table: String,
+    /// 有权限的字段
+    pub fields: Vec<FieldInfo>,
+    pub filter: Option<FilterNode>,
+    pub group_by: Vec<String>,
+    pub aggregates: Vec<Aggregate>,
+    /// 字段为分组字段和聚合的别名
+    pub having: Option<FilterNode>,
+    /// 过滤条件的复杂度限制
+    pub limits: FilterLimits,
+    /// 见 [`Sql::array_min_len`]，`filter` 和 `having` 都适用
+    pub array_min_len: Option<usize>,
+}
+
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    let first = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    first && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Aggregate {
+    /// 聚合结果的字段信息，用于检查 HAVING
+    fn info(&self, infos: &HashMap<String, FieldInfo>) -> Result<FieldInfo, DataAccessErr> {
+        let field = match (&self.field, self.func) {
+            (None, AggFn::Count) => None,
+            (None, _) => return Err(DataAccessErr::InvalidFormat),
+            (Some(name), _) => {
+                let info = infos.get(name).ok_or(DataAccessErr::UnknownField)?;
+                if !info.aggregatable {
+                    return Err(DataAccessErr::InvalidOperation);
+                }
+                Some(info)
+            }
+        };
+        let numeric = |info: &FieldInfo| match info.type_ {
+            FieldType::Int | FieldType::Num | FieldType::Decimal { .. } => Ok(info.type_.clone()),
+            _ => Err(DataAccessErr::TypeErr),
+        };
+        let type_ = match (self.func, field) {
+            (AggFn::Count, _) => FieldType::Int,
+            (AggFn::Sum, Some(info)) => numeric(info)?,
+            // 定点小数的平均值仍是定点小数，HAVING 中精确比较
+            (AggFn::Avg, Some(info)) => match numeric(info)? {
+                ft @ FieldType::Decimal { .. } => ft,
+                _ => FieldType::Num,
+            },
+            (AggFn::Min | AggFn::Max, Some(info)) => info.type_.clone(),
+            (_, None) => return Err(DataAccessErr::InvalidFormat),
+        };
+        Ok(FieldInfo {
+            name: self.alias.clone(),
+            text: self.alias.clone(),
+            // 没有行时 SUM、MIN 等为 NULL
+            nullable: self.func != AggFn::Count,
+            type_,
+            operation: " Equal Cmp In ".into(),
+            sortable: true,
+            aggregatable: false,
+        })
+    }
+
+    fn write(&self, sql: &mut Sql) {
+        sql.push(match self.func {
+            AggFn::Count => "COUNT(",
+            AggFn::Sum => "SUM(",
+            AggFn::Avg => "AVG(",
+            AggFn::Min => "MIN(",
+            AggFn::Max => "MAX(",
+        });
+        match &self.field {
+            Some(field) => sql.ident(field),
+            None => sql.push("*"),
+        }
+        sql.push(")");
+    }
+}
+
+impl ReportQuery {
+    /// HAVING 可以使用的字段：分组字段和聚合结果
+    fn having_infos(
+        &self,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<HashMap<String, FieldInfo>, DataAccessErr> {
+        let mut res = HashMap::new();
+        for name in self.group_by.iter() {
+            let info = infos.get(name).ok_or(DataAccessErr::UnknownField)?;
+            if res.insert(name.clone(), info.clone()).is_some() {
+                return Err(DataAccessErr::InvalidFormat);
+            }
+        }
+        for agg in self.aggregates.iter() {
+            if !is_ident(&agg.alias) {
+                return Err(DataAccessErr::InvalidFormat);
+            }
+            if res.insert(agg.alias.clone(), agg.info(infos)?).is_some() {
+                return Err(DataAccessErr::InvalidFormat);
+            }
+        }
+        Ok(res)
+    }
+
+    /// 编译为参数化的 sql，`filter` 检查同 [`FilterNode::check`]，
+    /// `having` 中的别名会替换为聚合表达式
+    pub fn to_sql(
+        &self,
+        dialect: Dialect,
+        user_id: u64,
+        clock: &Clock,
+    ) -> Result<Sql, DataAccessErr> {
+        if self.group_by.is_empty() && self.aggregates.is_empty() {
+            return Err(DataAccessErr::InvalidFormat);
+        }
+        let infos = field_info_map(self.fields.clone());
+        let having_infos = self.having_infos(&infos)?;
+        if let Some(filter) = &self.filter {
+            filter.check_limits(&self.limits)?;
+            filter.check(&infos)?;
+        }
+        if let Some(having) = &self.having {
+            having.check_limits(&self.limits)?;
+            having.check(&having_infos)?;
+        }
+
+        let mut sql = Sql::new(dialect);
+        sql.array_min_len = self.array_min_len;
+        sql.push("SELECT ");
+        for (i, name) in self.group_by.iter().enumerate() {
+            if i > 0 {
+                sql.push(", ");
+            }
+            sql.ident(name);
+        }
+        for (i, agg) in self.aggregates.iter().enumerate() {
+            if i > 0 || !self.group_by.is_empty() {
+                sql.push(", ");
+            }
+            agg.write(&mut sql);
+            sql.push(" AS ");
+            sql.ident(&agg.alias);
+        }
+        sql.push(" FROM ");
+        sql.ident(&self.table);
+        if let Some(filter) = &self.filter {
+            let ctx = EvalContext {
+                infos: &infos,
+                user_id,
+                clock,
+            };
+            sql.push(" WHERE ");
+            sql.node(filter, &ctx)?;
+        }
+        for (i, name) in self.group_by.iter().enumerate() {
+            sql.push(if i == 0 { " GROUP BY " } else { ", " });
+            sql.ident(name);
+        }
+        if let Some(having) = &self.having {
+            // postgres 的 HAVING 不能引用别名
+            for agg in self.aggregates.iter() {
+                let mut expr = Sql::new(dialect);
+                agg.write(&mut expr);
+                sql.exprs.insert(agg.alias.clone(), expr.sql);
+            }
+            let ctx = EvalContext {
+                infos: &having_infos,
+                user_id,
+                clock,
+            };
+            sql.push(" HAVING ");
+            sql.node(having, &ctx)?;
+            sql.exprs.clear();
+        }
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, Bind::*, Clock, DataAccessErr, Dialect, FieldInfo};
+    use super::super::{test_info, FieldType as FT, FilterLimits, FilterNode};
+    use super::{AggFn, Aggregate, ReportQuery};
+    use chrono::{FixedOffset, NaiveDate};
+
+    fn fields() -> Vec<FieldInfo> {
+        let info = |name, type_, aggregatable| {
+            test_info(name, type_, " Equal Cmp In ").with_aggregatable(aggregatable)
+        };
+        vec![
+            info("city", FT::Str, false),
+            info("sex", FT::Enum("sex".into()), false),
+            info("amount", FT::Num, true),
+            info("remark", FT::Str, true),
+            info("order_time", FT::DateTime, false),
+        ]
+    }
+
+    fn agg(func: AggFn, field: Option<&str>, alias: &str) -> Aggregate {
+        Aggregate {
+            func,
+            field: field.map(String::from),
+            alias: alias.into(),
+        }
+    }
+
+    fn report(aggregates: Vec<Aggregate>, having: Option<&str>) -> ReportQuery {
+        let infos = field_info_map(fields());
+        let filter = "order_time >= '2022-01-01 00:00:00'";
+        let having_infos =
+            field_info_map(vec![test_info("cnt", FT::Int, " Cmp ").with_nullable(false)]);
+        ReportQuery {
+            table: "orders".into(),
+            fields: fields(),
+            filter: Some(FilterNode::parse(filter, &infos).unwrap()),
+            group_by: vec!["city".into()],
+            aggregates,
+            having: having.map(|s| FilterNode::parse(s, &having_infos).unwrap()),
+            limits: FilterLimits::default(),
+            array_min_len: None,
+        }
+    }
+
+    fn clock() -> Clock {
+        Clock::system(FixedOffset::east_opt(0).unwrap())
+    }
+
+    #[test]
+    fn to_sql() {
+        let aggs = vec![
+            agg(AggFn::Count, None, "cnt"),
+            agg(AggFn::Sum, Some("amount"), "total"),
+            agg(AggFn::Max, Some("remark"), "last_remark"),
+        ];
+        let sql = report(aggs, Some("cnt > 10"))
+            .to_sql(Dialect::Postgres, 1, &clock())
+            .unwrap();
+        assert_eq!(
+            sql.sql,
+            r#"SELECT "city", COUNT(*) AS "cnt", SUM("amount") AS "total", MAX("remark") AS "last_remark" FROM "orders" WHERE "order_time" >= $1 GROUP BY "city" HAVING COUNT(*) > $2"#
+        );
+        let time = NaiveDate::from_ymd_opt(2022, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0);
+        assert_eq!(sql.binds[..], [DateTime(time.unwrap()), Int(10)]);
+    }
+
+    #[test]
+    fn array() {
+        let mut q = report(vec![agg(AggFn::Count, None, "cnt")], None);
+        let infos = field_info_map(fields());
+        q.filter = Some(FilterNode::parse("city in ('a', 'b')", &infos).unwrap());
+        q.array_min_len = Some(2);
+        let sql = q.to_sql(Dialect::Postgres, 1, &clock()).unwrap();
+        assert_eq!(
+            sql.sql,
+            r#"SELECT "city", COUNT(*) AS "cnt" FROM "orders" WHERE "city" = ANY($1) GROUP BY "city""#
+        );
+        assert_eq!(sql.binds[..], [StrArray(vec!["a".into(), "b".into()])]);
+    }
+
+    #[test]
+    fn check() {
+        let err = |aggs, having| {
+            report(aggs, having)
+                .to_sql(Dialect::MySql, 1, &clock())
+                .unwrap_err()
+        };
+        let count = || agg(AggFn::Count, None, "cnt");
+        assert_eq!(
+            err(vec![agg(AggFn::Sum, Some("city"), "x")], None),
+            DataAccessErr::InvalidOperation
+        );
+        assert_eq!(
+            err(vec![agg(AggFn::Avg, Some("remark"), "x")], None),
+            DataAccessErr::TypeErr
+        );
+        assert_eq!(
+            err(vec![agg(AggFn::Sum, None, "x")], None),
+            DataAccessErr::InvalidFormat
+        );
+        assert_eq!(
+            err(vec![count(), agg(AggFn::Count, None, "cnt")], None),
+            DataAccessErr::InvalidFormat
+        );
+        assert_eq!(
+            err(vec![agg(AggFn::Count, None, "a b")], None),
+            DataAccessErr::InvalidFormat
+        );
+        // HAVING 只能使用分组字段和聚合结果
+        assert_eq!(
+            err(vec![agg(AggFn::Count, None, "n")], Some("cnt > 1")),
+            DataAccessErr::UnknownField
+        );
+    }
+}
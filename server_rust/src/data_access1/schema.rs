@@ -0,0 +1,286 @@
+use super::{Between, Cmp, Eq, FieldInfo, FieldType, In, Like, Logical, Not, Nullable};
+use serde::Serialize;
+use serde_json::{json, Map, Value as Json};
+
+/// 运算符序列化后的名字，和 serde 保持一致
+fn names<T: Serialize>(v: &[T]) -> Json {
+    let v = v.iter().map(|ope| serde_json::to_value(ope).unwrap());
+    Json::Array(v.collect())
+}
+
+fn tuple(items: Vec<Json>) -> Json {
+    let len = items.len();
+    json!({
+        "type": "array",
+        "items": items,
+        "minItems": len,
+        "maxItems": len,
+    })
+}
+
+fn tagged(type_: &str, value: Option<Json>) -> Json {
+    match value {
+        Some(value) => json!({
+            "type": "object",
+            "properties": { "type": { "const": type_ }, "value": value },
+            "required": ["type", "value"],
+            "additionalProperties": false,
+        }),
+        None => json!({
+            "type": "object",
+            "properties": { "type": { "const": type_ } },
+            "required": ["type"],
+            "additionalProperties": false,
+        }),
+    }
+}
+
+fn relative_time() -> Json {
+    let unit = json!({ "enum": ["Second", "Minute", "Hour", "Day", "Week", "Month", "Year"] });
+    json!({
+        "type": "object",
+        "properties": { "offset": { "type": "integer" }, "unit": unit, "trunc": unit },
+        "required": ["offset", "unit"],
+        "additionalProperties": false,
+    })
+}
+
+/// 定点小数，字符串要是十进制数字，同 [`super::Decimal`] 的解析，带小数时只能用字符串
+fn decimal() -> Json {
+    json!({
+        "type": ["string", "integer"],
+        "pattern": "^[+-]?(\\d+\\.?\\d*|\\.\\d+)([eE][+-]?\\d+)?$",
+    })
+}
+
+/// ISO-8601 时间，只检查开头的日期，完整的格式同 [`super::parse_datetime`]
+fn datetime() -> Json {
+    json!({ "type": "string", "pattern": "^\\s*\\d{4}-\\d{2}-\\d{2}" })
+}
+
+/// 字段可以使用的 [`super::Value`]，同 [`super::Value::check_field_type`]
+fn value_schema(info: &FieldInfo, infos: &[FieldInfo]) -> Json {
+    let uint = || json!({ "type": "integer", "minimum": 0 });
+    let string = || json!({ "type": "string" });
+    let mut v = match &info.type_ {
+        FieldType::Str => vec![tagged("Str", Some(string()))],
+        FieldType::Int => vec![tagged("Int", Some(uint()))],
+        FieldType::Num => vec![tagged("Num", Some(json!({ "type": "number" })))],
+        FieldType::Decimal { .. } => vec![tagged("Decimal", Some(decimal()))],
+        FieldType::Id => vec![tagged("Id", Some(uint())), tagged("CurrentUserId", None)],
+        FieldType::Enum(_) => vec![tagged("Int", Some(uint())), tagged("Str", Some(string()))],
+        // 时间字符串和时间戳可以互相转换
+        FieldType::DateTime | FieldType::UnixTiemstamp => vec![
+            tagged("DateTime", Some(datetime())),
+            tagged("UnixTiemstamp", Some(uint())),
+            tagged("CurrentDate", None),
+            tagged("CurrentTime", None),
+            tagged("RelativeTime", Some(relative_time())),
+        ],
+    };
+    let fields: Vec<_> = infos
+        .iter()
+        .filter(|f| f.name != info.name && f.type_ == info.type_)
+        .map(|f| Json::from(f.name.as_str()))
+        .collect();
+    if !fields.is_empty() {
+        v.push(tagged("Field", Some(json!({ "enum": fields }))));
+    }
+    json!({ "anyOf": v })
+}
+
+/// 字段可以使用的 [`super::ListValue`]，同 [`super::ListValue::chekc_type`]
+fn list_schema(ft: &FieldType) -> Json {
+    let list = |type_, item| tagged(type_, Some(json!({ "type": "array", "items": item })));
+    let uint = || json!({ "type": "integer", "minimum": 0 });
+    match ft {
+        FieldType::Str => list("Str", json!({ "type": "string" })),
+        FieldType::Enum(_) => json!({
+            "anyOf": [list("Int", uint()), list("Str", json!({ "type": "string" }))]
+        }),
+        FieldType::Int => list("Int", uint()),
+        FieldType::Id => list("Id", uint()),
+        FieldType::Num => list("Num", json!({ "type": "number" })),
+        FieldType::Decimal { .. } => list("Decimal", decimal()),
+        FieldType::DateTime | FieldType::UnixTiemstamp => json!({
+            "anyOf": [list("DateTime", datetime()), list("UnixTiemstamp", uint())]
+        }),
+    }
+}
+
+/// 字段允许的所有节点，同 [`super::FilterNode::check`]；
+/// 没有允许的节点时为 `None`，draft-07 中 `anyOf` 不能是空数组
+fn field_schema(info: &FieldInfo, infos: &[FieldInfo]) -> Option<Json> {
+    let name = || json!({ "const": info.name });
+    let has = |ope: &str| info.operation.contains(ope);
+    let mut v = vec![];
+    if info.nullable {
+        let ope = names(&[Nullable::IsNull, Nullable::IsNotNull]);
+        v.push(tuple(vec![json!({ "enum": ope }), name()]));
+    }
+    if has(" Equal ") {
+        let ope = names(&[Eq::Eq, Eq::NotEq]);
+        v.push(tuple(vec![
+            json!({ "enum": ope }),
+            name(),
+            value_schema(info, infos),
+        ]));
+    }
+    if has(" Cmp ") {
+        let ope = names(&[Cmp::Gt, Cmp::GtEq, Cmp::Lt, Cmp::LtEq]);
+        v.push(tuple(vec![
+            json!({ "enum": ope }),
+            name(),
+            value_schema(info, infos),
+        ]));
+        let ope = names(&[Between::Between, Between::GtEqLt, Between::GtLtEq]);
+        let value = value_schema(info, infos);
+        v.push(tuple(vec![
+            json!({ "enum": ope }),
+            name(),
+            value.clone(),
+            value,
+        ]));
+    }
+    if has(" In ") {
+        let ope = names(&[In::In, In::NotIn]);
+        v.push(tuple(vec![
+            json!({ "enum": ope }),
+            name(),
+            list_schema(&info.type_),
+        ]));
+    }
+    if has(" Like ") && info.type_ == FieldType::Str {
+        let ope = names(&[Like::StartWith, Like::Contains, Like::EndWith]);
+        v.push(tuple(vec![
+            json!({ "enum": ope }),
+            name(),
+            json!({ "type": "string" }),
+        ]));
+    }
+    (!v.is_empty()).then(|| json!({ "anyOf": v }))
+}
+
+/// 根据字段信息生成 [`super::FilterNode`] 的 JSON Schema (draft-07)
+///
+/// 字段名、每个字段可以使用的运算符和值类型都会被限制，前端和网关可以直接用来校验
+pub fn filter_schema(infos: &[FieldInfo]) -> Json {
+    let node = |name: &str| json!({ "$ref": format!("#/definitions/{}", name) });
+    let mut definitions = Map::new();
+    let mut nodes = vec![node("logical"), node("not")];
+    definitions.insert(
+        "logical".into(),
+        tuple(vec![
+            json!({ "enum": names(&[Logical::And, Logical::Or]) }),
+            json!({ "type": "array", "items": node("node") }),
+        ]),
+    );
+    definitions.insert(
+        "not".into(),
+        tuple(vec![json!({ "enum": names(&[Not::Not]) }), node("node")]),
+    );
+    for info in infos {
+        let schema = match field_schema(info, infos) {
+            Some(schema) => schema,
+            None => continue,
+        };
+        let key = format!("field:{}", info.name);
+        nodes.push(node(&key));
+        definitions.insert(key, schema);
+    }
+    definitions.insert("node".into(), json!({ "anyOf": nodes }));
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "$ref": "#/definitions/node",
+        "definitions": definitions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{test_info, FieldInfo, FieldType as FT};
+    use super::filter_schema;
+    use serde_json::json;
+
+    fn infos() -> Vec<FieldInfo> {
+        let info = |name, type_, nullable, operation| {
+            test_info(name, type_, operation).with_nullable(nullable)
+        };
+        vec![
+            info("name", FT::Str, true, " Equal  Like "),
+            info("owner_id", FT::Id, false, " Equal  In "),
+            info("assigned_to", FT::Id, false, " Equal "),
+        ]
+    }
+
+    #[test]
+    fn schema() {
+        let schema = filter_schema(&infos());
+        let defs = &schema["definitions"];
+        assert_eq!(defs["node"]["anyOf"].as_array().unwrap().len(), 5);
+        assert_eq!(
+            defs["logical"]["items"][0],
+            json!({ "enum": ["And", "Or"] })
+        );
+
+        let name = defs["field:name"]["anyOf"].as_array().unwrap();
+        assert_eq!(name.len(), 3);
+        assert_eq!(
+            name[0]["items"][0],
+            json!({ "enum": ["IsNull", "IsNotNull"] })
+        );
+        assert_eq!(name[0]["items"][1], json!({ "const": "name" }));
+        let like = json!({ "enum": ["StartWith", "Contains", "EndWith"] });
+        assert_eq!(name[2]["items"][0], like);
+
+        let owner = defs["field:owner_id"]["anyOf"].as_array().unwrap();
+        let values = owner[0]["items"][2]["anyOf"].as_array().unwrap();
+        let types: Vec<_> = values
+            .iter()
+            .map(|v| &v["properties"]["type"]["const"])
+            .collect();
+        assert_eq!(types, ["Id", "CurrentUserId", "Field"]);
+        assert_eq!(
+            values[2]["properties"]["value"],
+            json!({ "enum": ["assigned_to"] })
+        );
+        let list = &owner[1]["items"][2];
+        assert_eq!(list["properties"]["type"], json!({ "const": "Id" }));
+    }
+
+    /// 没有允许的运算符又不能为 NULL 的字段不生成节点，避免空的 `anyOf`
+    #[test]
+    fn field_without_operation() {
+        let mut infos = infos();
+        infos.push(test_info("secret", FT::Str, "").with_nullable(false));
+        let schema = filter_schema(&infos);
+        let defs = &schema["definitions"];
+        assert_eq!(defs["node"]["anyOf"].as_array().unwrap().len(), 5);
+        assert!(defs.get("field:secret").is_none());
+    }
+
+    /// 同 `Value::chekc_type`，时间字符串和时间戳在两种时间字段上都可以使用
+    #[test]
+    fn datetime_and_timestamp() {
+        let mut infos = infos();
+        for (name, type_) in [
+            ("created_at", FT::DateTime),
+            ("updated_at", FT::UnixTiemstamp),
+        ] {
+            infos.push(test_info(name, type_, " Cmp In ").with_nullable(false));
+        }
+        let schema = filter_schema(&infos);
+        let defs = &schema["definitions"];
+        let consts = |v: &serde_json::Value| -> Vec<_> {
+            let v = v["anyOf"].as_array().unwrap().iter();
+            v.map(|v| v["properties"]["type"]["const"].clone())
+                .collect()
+        };
+        for name in ["field:created_at", "field:updated_at"] {
+            let nodes = defs[name]["anyOf"].as_array().unwrap();
+            let types = consts(&nodes[0]["items"][2]);
+            assert_eq!(&types[..2], ["DateTime", "UnixTiemstamp"]);
+            assert_eq!(consts(&nodes[2]["items"][2]), ["DateTime", "UnixTiemstamp"]);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use super::sql::{Dialect, Sql};
+use super::{DataAccessErr, FieldInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// NULL 排在前面还是后面，不指定时使用数据库的默认行为
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortItem {
+    pub field: String,
+    #[serde(default)]
+    pub direction: Direction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nulls: Option<Nulls>,
+}
+
+/// 排序，代替前端传入的 ORDER BY 字符串
+///
+/// json 为 `[{ "field": "reg_time", "direction": "Desc", "nulls": "Last" }]`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SortSpec(pub Vec<SortItem>);
+
+impl SortItem {
+    pub fn asc(field: &str) -> Self {
+        Self {
+            field: field.into(),
+            direction: Direction::Asc,
+            nulls: None,
+        }
+    }
+
+    pub fn desc(field: &str) -> Self {
+        Self {
+            field: field.into(),
+            direction: Direction::Desc,
+            nulls: None,
+        }
+    }
+
+    pub fn nulls(self, nulls: Nulls) -> Self {
+        Self {
+            nulls: Some(nulls),
+            ..self
+        }
+    }
+}
+
+impl SortSpec {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 字段要存在且 [`FieldInfo::sortable`]，同一个字段不能出现两次
+    pub fn check(&self, infos: &HashMap<String, FieldInfo>) -> Result<(), DataAccessErr> {
+        for (i, item) in self.0.iter().enumerate() {
+            let info = infos.get(&item.field).ok_or(DataAccessErr::UnknownField)?;
+            if !info.sortable {
+                return Err(DataAccessErr::InvalidOperation);
+            }
+            if self.0[..i].iter().any(|x| x.field == item.field) {
+                return Err(DataAccessErr::InvalidFormat);
+            }
+        }
+        Ok(())
+    }
+
+    /// 写入 ` ORDER BY ...`，没有排序时不写
+    ///
+    /// mysql 不支持 `NULLS FIRST/LAST`，用 `f IS NULL` 排在前面实现
+    pub(super) fn write(&self, sql: &mut Sql, infos: &HashMap<String, FieldInfo>) {
+        for (i, item) in self.0.iter().enumerate() {
+            sql.push(if i == 0 { " ORDER BY " } else { ", " });
+            let nullable = infos.get(&item.field).is_some_and(|f| f.nullable);
+            let nulls = item.nulls.filter(|_| nullable);
+            if let (Dialect::MySql, Some(nulls)) = (sql.dialect, nulls) {
+                sql.ident(&item.field);
+                sql.push(match nulls {
+                    Nulls::First => " IS NULL DESC, ",
+                    Nulls::Last => " IS NULL ASC, ",
+                });
+            }
+            sql.ident(&item.field);
+            sql.push(match item.direction {
+                Direction::Asc => " ASC",
+                Direction::Desc => " DESC",
+            });
+            match (sql.dialect, nulls) {
+                (Dialect::MySql, _) | (_, None) => {}
+                (_, Some(Nulls::First)) => sql.push(" NULLS FIRST"),
+                (_, Some(Nulls::Last)) => sql.push(" NULLS LAST"),
+            }
+        }
+    }
+
+    /// 检查并编译为 ` ORDER BY ...`
+    pub fn to_sql(
+        &self,
+        dialect: Dialect,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<Sql, DataAccessErr> {
+        self.check(infos)?;
+        let mut sql = Sql::new(dialect);
+        self.write(&mut sql, infos);
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_info;
+    use super::super::{field_info_map, DataAccessErr, Dialect, FieldInfo, FieldType as FT};
+    use super::{Nulls, SortItem, SortSpec};
+    use std::collections::HashMap;
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let info = |name, nullable, sortable| {
+            test_info(name, FT::Int, "")
+                .with_nullable(nullable)
+                .with_sortable(sortable)
+        };
+        field_info_map(vec![
+            info("id", false, true),
+            info("age", true, true),
+            info("remark", true, false),
+        ])
+    }
+
+    #[test]
+    fn serde() {
+        let json = r#"[{"field":"age","direction":"Desc","nulls":"Last"},{"field":"id"}]"#;
+        let spec: SortSpec = serde_json::from_str(json).unwrap();
+        let expect = SortSpec(vec![
+            SortItem::desc("age").nulls(Nulls::Last),
+            SortItem::asc("id"),
+        ]);
+        assert_eq!(spec, expect);
+        assert_eq!(
+            serde_json::to_string(&spec).unwrap(),
+            r#"[{"field":"age","direction":"Desc","nulls":"Last"},{"field":"id","direction":"Asc"}]"#
+        );
+    }
+
+    #[test]
+    fn to_sql() {
+        let spec = SortSpec(vec![
+            SortItem::desc("age").nulls(Nulls::Last),
+            SortItem::asc("id").nulls(Nulls::First),
+        ]);
+        let sql = |dialect| spec.to_sql(dialect, &infos()).unwrap().sql;
+        assert_eq!(
+            sql(Dialect::Postgres),
+            r#" ORDER BY "age" DESC NULLS LAST, "id" ASC"#
+        );
+        assert_eq!(
+            sql(Dialect::MySql),
+            " ORDER BY `age` IS NULL ASC, `age` DESC, `id` ASC"
+        );
+        assert_eq!(
+            SortSpec::default()
+                .to_sql(Dialect::Sqlite, &infos())
+                .unwrap()
+                .sql,
+            ""
+        );
+    }
+
+    #[test]
+    fn check() {
+        let check = |v: Vec<SortItem>| SortSpec(v).check(&infos());
+        assert_eq!(
+            check(vec![SortItem::asc("remark")]),
+            Err(DataAccessErr::InvalidOperation)
+        );
+        assert_eq!(
+            check(vec![SortItem::asc("name")]),
+            Err(DataAccessErr::UnknownField)
+        );
+        let dup = vec![SortItem::asc("id"), SortItem::desc("id")];
+        assert_eq!(check(dup), Err(DataAccessErr::InvalidFormat));
+    }
+}
@@ -0,0 +1,560 @@
+use super::time::{instant, parse_datetime};
+use super::{
+    Between, DataAccessErr, Decimal, EvalContext, Field, FieldType, FilterLimits, FilterNode, In,
+    Like, ListValue, Logical, StrValue, Value,
+};
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use smallvec::SmallVec;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult, Write};
+
+/// 数据库方言，决定标识符的引号和参数的占位符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dialect {
+    /// 反引号，`?` 占位
+    MySql,
+    /// 双引号，`$1` 占位
+    Postgres,
+    /// 双引号，`?` 占位
+    Sqlite,
+}
+
+impl Dialect {
+    /// 常量 TRUE/FALSE，sqlite 3.23 之前没有 `TRUE` 关键字
+    pub fn bool_literal(self, b: bool) -> &'static str {
+        match (self, b) {
+            (Dialect::MySql | Dialect::Postgres, true) => "TRUE",
+            (Dialect::MySql | Dialect::Postgres, false) => "FALSE",
+            (Dialect::Sqlite, true) => "1",
+            (Dialect::Sqlite, false) => "0",
+        }
+    }
+}
+
+/// 绑定的参数
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bind {
+    Int(i64),
+    Num(f64),
+    /// 定点小数，小数位数已补齐到字段的 `scale`，驱动按 `NUMERIC` 绑定，
+    /// 或者以 [`Decimal`] 的字符串形式绑定
+    Decimal(Decimal),
+    Str(String),
+    /// `DateTime` 字段的值，已转到 [`Clock::tz`](super::Clock::tz) 时区
+    DateTime(NaiveDateTime),
+    /// postgres 的数组参数，见 [`Sql::array_min_len`]
+    IntArray(Vec<i64>),
+    NumArray(Vec<f64>),
+    DecimalArray(Vec<Decimal>),
+    StrArray(Vec<String>),
+    DateTimeArray(Vec<NaiveDateTime>),
+}
+
+/// 绑定的参数，大部分条件的参数不多，放在栈上
+pub type Binds = SmallVec<[Bind; 8]>;
+
+/// 参数化的 sql，值都在 `binds` 中，`sql` 只包含字段名和占位符
+///
+/// sql 文本直接写入 `sql` 缓冲区，不产生中间字符串；字符串参数和 Like 的模式
+/// 仍要复制为 [`Bind::Str`]。热点接口可以通过 [`Sql::clear`] 复用同一个缓冲区
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sql {
+    pub dialect: Dialect,
+    pub sql: String,
+    pub binds: Binds,
+    /// 字段名替换为表达式，例如报表 HAVING 中的聚合别名
+    pub(super) exprs: HashMap<String, String>,
+    /// postgres 中长度达到它的 `In` 列表绑定为一个数组参数，
+    /// 写成 `= ANY($1)`/`<> ALL($1)`，语句的文本不随列表长度变化
+    pub array_min_len: Option<usize>,
+}
+
+/// Like 的转义字符，mysql 中 `\` 在字符串里也要转义，所以用 `!`
+const LIKE_ESCAPE: char = '!';
+
+pub(super) fn like_pattern(ope: &Like, v: &StrValue) -> String {
+    let mut s = String::with_capacity(v.0.len() + 2);
+    if !matches!(ope, Like::StartWith) {
+        s.push('%');
+    }
+    for c in v.0.chars() {
+        if c == LIKE_ESCAPE || c == '%' || c == '_' {
+            s.push(LIKE_ESCAPE);
+        }
+        s.push(c);
+    }
+    if !matches!(ope, Like::EndWith) {
+        s.push('%');
+    }
+    s
+}
+
+/// 数据库中没有无符号整数，超过 i64 的值视为类型错误
+pub(super) fn uint(v: u64) -> Result<Bind, DataAccessErr> {
+    i64::try_from(v)
+        .map(Bind::Int)
+        .map_err(|_| DataAccessErr::TypeErr)
+}
+
+/// 定点小数补齐字段的小数位数，`18` 绑定为 `18.00`
+fn decimal_bind(v: Decimal, ft: &FieldType) -> Bind {
+    match ft {
+        FieldType::Decimal { scale } => Bind::Decimal(v.with_scale(*scale)),
+        _ => Bind::Decimal(v),
+    }
+}
+
+/// 时间按字段类型绑定，`DateTime` 为时区内的时间，`UnixTiemstamp` 为时间戳
+fn time_bind(time: DateTime<FixedOffset>, ft: &FieldType) -> Result<Bind, DataAccessErr> {
+    match ft {
+        FieldType::DateTime => Ok(Bind::DateTime(time.naive_local())),
+        FieldType::UnixTiemstamp => Ok(Bind::Int(time.timestamp())),
+        _ => Err(DataAccessErr::TypeErr),
+    }
+}
+
+/// 值对应的参数，字段引用没有参数，返回 `None`
+pub(super) fn value_bind(
+    v: &Value,
+    ft: &FieldType,
+    ctx: &EvalContext,
+) -> Result<Option<Bind>, DataAccessErr> {
+    v.check_field_type(ft, ctx.infos)?;
+    if let Some(time) = instant(v, ctx.clock)? {
+        return time_bind(time, ft).map(Some);
+    }
+    Ok(Some(match v {
+        Value::Id(v) | Value::Int(v) => uint(*v)?,
+        Value::Num(v) => Bind::Num(*v),
+        Value::Decimal(v) => decimal_bind(*v, ft),
+        Value::Str(v) => Bind::Str(v.0.clone()),
+        Value::CurrentUserId => uint(ctx.user_id)?,
+        Value::Field(_) => return Ok(None),
+        _ => return Err(DataAccessErr::TypeErr),
+    }))
+}
+
+/// 整个列表作为一个数组参数
+pub(super) fn array_bind(
+    v: &ListValue,
+    ft: &FieldType,
+    ctx: &EvalContext,
+) -> Result<Bind, DataAccessErr> {
+    let (mut ints, mut nums, mut strs, mut times) = (vec![], vec![], vec![], vec![]);
+    let mut decimals = vec![];
+    list_binds(v, ft, ctx, |b| match b {
+        Bind::Int(v) => ints.push(v),
+        Bind::Num(v) => nums.push(v),
+        Bind::Decimal(v) => decimals.push(v),
+        Bind::Str(v) => strs.push(v),
+        Bind::DateTime(v) => times.push(v),
+        _ => {}
+    })?;
+    Ok(match ft {
+        FieldType::Num => Bind::NumArray(nums),
+        FieldType::Decimal { .. } => Bind::DecimalArray(decimals),
+        FieldType::Enum(_) if matches!(v, ListValue::Int(_)) => Bind::IntArray(ints),
+        FieldType::Str | FieldType::Enum(_) => Bind::StrArray(strs),
+        FieldType::DateTime => Bind::DateTimeArray(times),
+        FieldType::Id | FieldType::Int | FieldType::UnixTiemstamp => Bind::IntArray(ints),
+    })
+}
+
+/// 依次取出列表的参数，时间同 [`value_bind`]
+pub(super) fn list_binds(
+    v: &ListValue,
+    ft: &FieldType,
+    ctx: &EvalContext,
+    mut f: impl FnMut(Bind),
+) -> Result<(), DataAccessErr> {
+    match v {
+        ListValue::Id(v) | ListValue::Int(v) => {
+            for v in v.iter() {
+                f(uint(*v)?);
+            }
+        }
+        ListValue::Num(v) => v.iter().for_each(|v| f(Bind::Num(*v))),
+        ListValue::Decimal(v) => v.iter().for_each(|v| f(decimal_bind(*v, ft))),
+        ListValue::Str(v) => v.iter().for_each(|v| f(Bind::Str(v.0.clone()))),
+        ListValue::DateTime(v) => {
+            for s in v.iter() {
+                f(time_bind(parse_datetime(&s.0, ctx.clock.tz)?, ft)?);
+            }
+        }
+        ListValue::UnixTiemstamp(v) => {
+            for ts in v.iter() {
+                let time = instant(&Value::UnixTiemstamp(*ts), ctx.clock)?;
+                f(time_bind(time.ok_or(DataAccessErr::TypeErr)?, ft)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Sql {
+    pub fn new(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            sql: String::new(),
+            binds: Binds::new(),
+            exprs: HashMap::new(),
+            array_min_len: None,
+        }
+    }
+
+    /// 清空内容，保留已分配的空间
+    pub fn clear(&mut self) {
+        self.sql.clear();
+        self.binds.clear();
+        self.exprs.clear();
+    }
+
+    pub fn push(&mut self, s: &str) {
+        self.sql.push_str(s);
+    }
+
+    /// 写入 `String` 不会失败
+    fn write(&mut self, args: std::fmt::Arguments) {
+        let _ = self.sql.write_fmt(args);
+    }
+
+    /// 加引号的标识符，`schema.table` 分别加引号
+    pub fn ident(&mut self, name: &str) {
+        let quote = match self.dialect {
+            Dialect::MySql => '`',
+            Dialect::Postgres | Dialect::Sqlite => '"',
+        };
+        for (i, part) in name.split('.').enumerate() {
+            if i > 0 {
+                self.sql.push('.');
+            }
+            self.sql.push(quote);
+            for c in part.chars() {
+                if c == quote {
+                    self.sql.push(quote);
+                }
+                self.sql.push(c);
+            }
+            self.sql.push(quote);
+        }
+    }
+
+    /// 添加参数并写入占位符
+    pub fn bind(&mut self, v: Bind) {
+        self.binds.push(v);
+        match self.dialect {
+            Dialect::MySql | Dialect::Sqlite => self.sql.push('?'),
+            Dialect::Postgres => {
+                let n = self.binds.len();
+                self.write(format_args!("${}", n));
+            }
+        }
+    }
+
+    fn field(&mut self, field: &Field) {
+        match self.exprs.get(&field.0) {
+            Some(expr) => self.sql.push_str(expr),
+            None => self.ident(&field.0),
+        }
+    }
+
+    fn value(&mut self, v: &Value, ft: &FieldType, ctx: &EvalContext) -> Result<(), DataAccessErr> {
+        match (value_bind(v, ft, ctx)?, v) {
+            (Some(b), _) => self.bind(b),
+            (None, Value::Field(f)) => self.field(f),
+            (None, _) => {}
+        }
+        Ok(())
+    }
+
+    pub(super) fn use_array(&self, v: &ListValue) -> bool {
+        self.dialect == Dialect::Postgres && self.array_min_len.is_some_and(|n| v.len() >= n)
+    }
+
+    fn list(
+        &mut self,
+        v: &ListValue,
+        ft: &FieldType,
+        ctx: &EvalContext,
+    ) -> Result<(), DataAccessErr> {
+        self.push("(");
+        let mut i = 0;
+        list_binds(v, ft, ctx, |b| {
+            if i > 0 {
+                self.push(", ");
+            }
+            i += 1;
+            self.bind(b);
+        })?;
+        self.push(")");
+        Ok(())
+    }
+
+    /// 写入条件，不检查字段的操作权限，值的类型仍然会检查
+    pub(super) fn node(
+        &mut self,
+        node: &FilterNode,
+        ctx: &EvalContext,
+    ) -> Result<(), DataAccessErr> {
+        let ft = |field: &Field| {
+            let info = ctx.infos.get(&field.0).ok_or(DataAccessErr::UnknownField)?;
+            Ok::<_, DataAccessErr>(&info.type_)
+        };
+        match node {
+            FilterNode::Logical(ope, v) if v.is_empty() => {
+                self.push(self.dialect.bool_literal(*ope == Logical::And))
+            }
+            FilterNode::Logical(ope, v) => {
+                self.push("(");
+                for (i, n) in v.iter().enumerate() {
+                    if i > 0 {
+                        self.write(format_args!("{}", ope));
+                    }
+                    self.node(n, ctx)?;
+                }
+                self.push(")");
+            }
+            FilterNode::Not(ope, n) => {
+                self.write(format_args!("{} (", ope));
+                self.node(n, ctx)?;
+                self.push(")");
+            }
+            FilterNode::Nullable(ope, field) => {
+                ft(field)?;
+                self.field(field);
+                self.write(format_args!(" {}", ope));
+            }
+            FilterNode::Equal(ope, field, v) => {
+                self.field(field);
+                self.write(format_args!(" {} ", ope));
+                self.value(v, ft(field)?, ctx)?;
+            }
+            FilterNode::Cmp(ope, field, v) => {
+                self.field(field);
+                self.write(format_args!(" {} ", ope));
+                self.value(v, ft(field)?, ctx)?;
+            }
+            FilterNode::Between(Between::Between, field, lo, hi) => {
+                self.field(field);
+                self.push(" BETWEEN ");
+                self.value(lo, ft(field)?, ctx)?;
+                self.push(" AND ");
+                self.value(hi, ft(field)?, ctx)?;
+            }
+            FilterNode::Between(ope, field, lo, hi) => {
+                let (lo_ope, hi_ope) = ope.bounds();
+                self.push("(");
+                self.field(field);
+                self.write(format_args!(" {} ", lo_ope));
+                self.value(lo, ft(field)?, ctx)?;
+                self.push(" AND ");
+                self.field(field);
+                self.write(format_args!(" {} ", hi_ope));
+                self.value(hi, ft(field)?, ctx)?;
+                self.push(")");
+            }
+            // `IN ()` 不是合法的 sql
+            FilterNode::In(ope, field, v) if v.is_empty() => {
+                ft(field)?;
+                self.push(self.dialect.bool_literal(*ope == In::NotIn));
+            }
+            FilterNode::In(ope, field, v) if self.use_array(v) => {
+                v.chekc_type(ft(field)?)?;
+                self.field(field);
+                self.push(match ope {
+                    In::In => " = ANY(",
+                    In::NotIn => " <> ALL(",
+                });
+                self.bind(array_bind(v, ft(field)?, ctx)?);
+                self.push(")");
+            }
+            FilterNode::In(ope, field, v) => {
+                v.chekc_type(ft(field)?)?;
+                self.field(field);
+                self.write(format_args!(" {} ", ope));
+                self.list(v, ft(field)?, ctx)?;
+            }
+            FilterNode::Like(ope, field, v) => {
+                ft(field)?;
+                self.field(field);
+                self.push(" LIKE ");
+                self.bind(Bind::Str(like_pattern(ope, v)));
+                self.write(format_args!(" ESCAPE '{}'", LIKE_ESCAPE));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Sql {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.sql)
+    }
+}
+
+impl FilterNode {
+    /// 编译为参数化的 WHERE 条件，先检查 `limits`，再检查同 [`FilterNode::check`]
+    pub fn to_sql(
+        &self,
+        dialect: Dialect,
+        ctx: &EvalContext,
+        limits: &FilterLimits,
+    ) -> Result<Sql, DataAccessErr> {
+        let mut sql = Sql::new(dialect);
+        self.write_sql(&mut sql, ctx, limits)?;
+        Ok(sql)
+    }
+
+    /// 同 [`FilterNode::to_sql`]，追加到已有的 `sql` 中，参数的序号接着已有的参数
+    pub fn write_sql(
+        &self,
+        sql: &mut Sql,
+        ctx: &EvalContext,
+        limits: &FilterLimits,
+    ) -> Result<(), DataAccessErr> {
+        self.check_limits(limits)?;
+        self.check(ctx.infos)?;
+        sql.node(self, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        field_info_map, test_info, Clock, EvalContext, FieldInfo, FieldType as FT, FilterLimits,
+    };
+    use super::{Bind, Dialect, FilterNode, Sql};
+    use chrono::{FixedOffset, NaiveDate, TimeZone, Utc};
+    use std::collections::HashMap;
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let info = |name, type_| test_info(name, type_, " Equal Cmp In Like ");
+        field_info_map(vec![
+            info("age", FT::Int),
+            info("name", FT::Str),
+            info("owner_id", FT::Id),
+            info("reg_time", FT::DateTime),
+            info("amount", FT::Decimal { scale: 2 }),
+            info("sex", FT::Enum("sex".into())),
+        ])
+    }
+
+    fn sql(src: &str, dialect: Dialect) -> (String, Vec<Bind>) {
+        let infos = infos();
+        let clock = Clock {
+            now: Utc.with_ymd_and_hms(2022, 3, 15, 18, 30, 0).unwrap(),
+            tz: FixedOffset::east_opt(8 * 3600).unwrap(),
+        };
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 7,
+            clock: &clock,
+        };
+        let limits = FilterLimits::default();
+        let node = FilterNode::parse(src, &infos).unwrap();
+        let sql = node.to_sql(dialect, &ctx, &limits).unwrap();
+        (sql.sql, sql.binds.to_vec())
+    }
+
+    #[test]
+    fn to_sql() {
+        use Bind::*;
+        let time = |d| {
+            let date = NaiveDate::from_ymd_opt(2022, 3, d).unwrap();
+            date.and_hms_opt(0, 0, 0).unwrap()
+        };
+        let src = r"age >= 18 and (name like '10\%!_%' or owner_id = current_user_id)";
+        assert_eq!(
+            sql(src, Dialect::Postgres),
+            (
+                r#"("age" >= $1 AND ("name" LIKE $2 ESCAPE '!' OR "owner_id" = $3))"#.into(),
+                vec![Int(18), Str("10!%!!!_%".into()), Int(7)]
+            )
+        );
+        assert_eq!(
+            sql(
+                "not age in (1, 2) and reg_time between [relative(-1, Day, Day), current_date)",
+                Dialect::MySql
+            ),
+            (
+                "(NOT (`age` IN (?, ?)) AND (`reg_time` >= ? AND `reg_time` < ?))".into(),
+                vec![Int(1), Int(2), DateTime(time(15)), DateTime(time(16))]
+            )
+        );
+        assert_eq!(
+            sql("age in () or name is null", Dialect::Sqlite),
+            (r#"(0 OR "name" IS NULL)"#.into(), vec![])
+        );
+    }
+
+    #[test]
+    fn empty_list_and_array() {
+        use Bind::*;
+        let infos = infos();
+        let clock = Clock::system(FixedOffset::east_opt(0).unwrap());
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 7,
+            clock: &clock,
+        };
+        let limits = FilterLimits::default();
+        let node = FilterNode::parse("age in () or name not in ()", &infos).unwrap();
+        let sql = |dialect| node.to_sql(dialect, &ctx, &limits).unwrap().sql;
+        assert_eq!(sql(Dialect::Postgres), "(FALSE OR TRUE)");
+        assert_eq!(sql(Dialect::Sqlite), "(0 OR 1)");
+
+        let node = FilterNode::parse("age in (1, 2, 3) and name not in ('a', 'b')", &infos);
+        let mut sql = Sql::new(Dialect::Postgres);
+        sql.array_min_len = Some(3);
+        node.unwrap().write_sql(&mut sql, &ctx, &limits).unwrap();
+        assert_eq!(sql.sql, r#"("age" = ANY($1) AND "name" NOT IN ($2, $3))"#);
+        assert_eq!(sql.binds[0], IntArray(vec![1, 2, 3]));
+
+        let node = FilterNode::parse("name not in ('a', 'b', 'c')", &infos).unwrap();
+        sql.clear();
+        node.write_sql(&mut sql, &ctx, &limits).unwrap();
+        assert_eq!(sql.sql, r#""name" <> ALL($1)"#);
+        assert_eq!(
+            sql.binds[..],
+            [StrArray(vec!["a".into(), "b".into(), "c".into()])]
+        );
+
+        // 整数的枚举绑定为整数数组
+        let node = FilterNode::parse("sex in (1, 2, 3)", &infos).unwrap();
+        sql.clear();
+        node.write_sql(&mut sql, &ctx, &limits).unwrap();
+        assert_eq!(sql.binds[..], [IntArray(vec![1, 2, 3])]);
+    }
+
+    #[test]
+    fn decimal() {
+        // 小数位数补齐到字段的 scale，更多的小数位原样保留
+        let text = |b: &Bind| match b {
+            Bind::Decimal(d) => vec![d.to_string()],
+            Bind::DecimalArray(v) => v.iter().map(|d| d.to_string()).collect(),
+            b => panic!("{:?}", b),
+        };
+        let src = "amount >= 18 and amount < 0.30000000000000001";
+        let (s, binds) = sql(src, Dialect::MySql);
+        assert_eq!(s, "(`amount` >= ? AND `amount` < ?)");
+        assert_eq!(
+            binds.iter().flat_map(text).collect::<Vec<_>>(),
+            ["18.00", "0.30000000000000001"]
+        );
+
+        let infos = infos();
+        let clock = Clock::system(FixedOffset::east_opt(0).unwrap());
+        let ctx = EvalContext {
+            infos: &infos,
+            user_id: 7,
+            clock: &clock,
+        };
+        let limits = FilterLimits::default();
+        let node = FilterNode::parse("amount in (1, 2.5)", &infos).unwrap();
+        let mut sql = Sql::new(Dialect::Postgres);
+        sql.array_min_len = Some(2);
+        node.write_sql(&mut sql, &ctx, &limits).unwrap();
+        assert_eq!(sql.sql, r#""amount" = ANY($1)"#);
+        assert_eq!(text(&sql.binds[0]), ["1.00", "2.50"]);
+    }
+}
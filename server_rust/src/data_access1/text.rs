@@ -0,0 +1,383 @@
+use super::{
+    Between, Cmp, Decimal, Eq, Field, FieldInfo, FieldType, FilterNode, In, Like, ListValue,
+    Logical, Nullable, RelativeTime, TimeUnit, Value,
+};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// 枚举名 => (值 => 显示文字)
+pub type EnumLabels = HashMap<String, HashMap<String, String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    Zh,
+    En,
+}
+
+/// 过滤条件的自然语言描述，用于已保存的过滤条件列表和审计日志
+///
+/// `年龄 ≥ 18 且 (性别 = 女 或 姓名 包含 张)`
+pub struct FilterText<'a> {
+    node: &'a FilterNode,
+    infos: &'a HashMap<String, FieldInfo>,
+    labels: &'a EnumLabels,
+    lang: Lang,
+}
+
+impl FilterNode {
+    pub fn text<'a>(
+        &'a self,
+        infos: &'a HashMap<String, FieldInfo>,
+        labels: &'a EnumLabels,
+        lang: Lang,
+    ) -> FilterText<'a> {
+        FilterText {
+            node: self,
+            infos,
+            labels,
+            lang,
+        }
+    }
+}
+
+impl Lang {
+    fn logical(self, ope: &Logical) -> &'static str {
+        match (self, ope) {
+            (Lang::Zh, Logical::And) => " 且 ",
+            (Lang::Zh, Logical::Or) => " 或 ",
+            (Lang::En, Logical::And) => " and ",
+            (Lang::En, Logical::Or) => " or ",
+        }
+    }
+
+    fn not(self) -> &'static str {
+        match self {
+            Lang::Zh => "非",
+            Lang::En => "not",
+        }
+    }
+
+    fn nullable(self, ope: &Nullable) -> &'static str {
+        match (self, ope) {
+            (Lang::Zh, Nullable::IsNull) => "为空",
+            (Lang::Zh, Nullable::IsNotNull) => "不为空",
+            (Lang::En, Nullable::IsNull) => "is empty",
+            (Lang::En, Nullable::IsNotNull) => "is not empty",
+        }
+    }
+
+    fn in_(self, ope: &In) -> &'static str {
+        match (self, ope) {
+            (Lang::Zh, In::In) => "属于",
+            (Lang::Zh, In::NotIn) => "不属于",
+            (Lang::En, In::In) => "in",
+            (Lang::En, In::NotIn) => "not in",
+        }
+    }
+
+    fn like(self, ope: &Like) -> &'static str {
+        match (self, ope) {
+            (Lang::Zh, Like::StartWith) => "开始为",
+            (Lang::Zh, Like::Contains) => "包含",
+            (Lang::Zh, Like::EndWith) => "结束为",
+            (Lang::En, Like::StartWith) => "starts with",
+            (Lang::En, Like::Contains) => "contains",
+            (Lang::En, Like::EndWith) => "ends with",
+        }
+    }
+
+    fn unit_name(self, unit: TimeUnit) -> &'static str {
+        use TimeUnit::*;
+        match (self, unit) {
+            (Lang::Zh, Second) => "秒",
+            (Lang::Zh, Minute) => "分钟",
+            (Lang::Zh, Hour) => "小时",
+            (Lang::Zh, Day) => "天",
+            (Lang::Zh, Week) => "周",
+            (Lang::Zh, Month) => "个月",
+            (Lang::Zh, Year) => "年",
+            (Lang::En, Second) => "second",
+            (Lang::En, Minute) => "minute",
+            (Lang::En, Hour) => "hour",
+            (Lang::En, Day) => "day",
+            (Lang::En, Week) => "week",
+            (Lang::En, Month) => "month",
+            (Lang::En, Year) => "year",
+        }
+    }
+
+    fn unit(self, unit: TimeUnit, n: u32) -> String {
+        match (self, n) {
+            (Lang::En, 1) | (Lang::Zh, _) => format!("{} {}", n, self.unit_name(unit)),
+            (Lang::En, _) => format!("{} {}s", n, self.unit_name(unit)),
+        }
+    }
+
+    fn relative_time(self, rt: &RelativeTime) -> String {
+        use TimeUnit::*;
+        let base = rt.trunc.map(|unit| match (self, unit) {
+            (Lang::Zh, Day) => "今天开始".to_string(),
+            (Lang::Zh, Month) => "本月开始".to_string(),
+            (Lang::Zh, Year) => "今年开始".to_string(),
+            (Lang::Zh, unit) => format!("本{}开始", self.unit_name(unit)),
+            (Lang::En, Day) => "start of today".to_string(),
+            (Lang::En, unit) => format!("start of this {}", self.unit_name(unit)),
+        });
+        let n = rt.offset.unsigned_abs();
+        let offset = self.unit(rt.unit, n);
+        match (self, base, rt.offset) {
+            (Lang::Zh, None, 0) => "现在".to_string(),
+            (Lang::Zh, None, o) if o < 0 => format!("{}前", offset),
+            (Lang::Zh, None, _) => format!("{}后", offset),
+            (Lang::Zh, Some(base), 0) => base,
+            (Lang::Zh, Some(base), o) if o < 0 => format!("{} {}前", base, offset),
+            (Lang::Zh, Some(base), _) => format!("{} {}后", base, offset),
+            (Lang::En, None, 0) => "now".to_string(),
+            (Lang::En, None, o) if o < 0 => format!("{} ago", offset),
+            (Lang::En, None, _) => format!("in {}", offset),
+            (Lang::En, Some(base), 0) => base,
+            (Lang::En, Some(base), o) if o < 0 => format!("{} before {}", offset, base),
+            (Lang::En, Some(base), _) => format!("{} after {}", offset, base),
+        }
+    }
+}
+
+impl FilterText<'_> {
+    fn info(&self, field: &Field) -> Option<&FieldInfo> {
+        self.infos.get(&field.0)
+    }
+
+    fn field(&self, field: &Field) -> String {
+        match self.info(field) {
+            Some(info) => info.text.clone(),
+            None => field.0.clone(),
+        }
+    }
+
+    fn label(&self, field: &Field, key: String) -> String {
+        let enum_name = match self.info(field).map(|info| &info.type_) {
+            Some(FieldType::Enum(name)) => name,
+            _ => return key,
+        };
+        let label = self.labels.get(enum_name).and_then(|l| l.get(&key));
+        label.cloned().unwrap_or(key)
+    }
+
+    /// 定点小数按字段的小数位数显示，例如金额 `18.00`
+    fn decimal(&self, field: &Field, v: &Decimal) -> String {
+        match self.info(field).map(|info| &info.type_) {
+            Some(FieldType::Decimal { scale }) => v.with_scale(*scale).to_string(),
+            _ => v.to_string(),
+        }
+    }
+
+    fn value(&self, field: &Field, v: &Value) -> String {
+        let lang = self.lang;
+        match v {
+            Value::Id(v) | Value::Int(v) | Value::UnixTiemstamp(v) => {
+                self.label(field, v.to_string())
+            }
+            Value::Num(v) => v.to_string(),
+            Value::Decimal(v) => self.decimal(field, v),
+            Value::Str(v) | Value::DateTime(v) => self.label(field, v.0.clone()),
+            Value::Field(v) => self.field(v),
+            Value::RelativeTime(v) => lang.relative_time(v),
+            Value::CurrentUserId if lang == Lang::Zh => "当前用户".to_string(),
+            Value::CurrentDate if lang == Lang::Zh => "今天".to_string(),
+            Value::CurrentTime if lang == Lang::Zh => "现在".to_string(),
+            Value::CurrentUserId => "current user".to_string(),
+            Value::CurrentDate => "today".to_string(),
+            Value::CurrentTime => "now".to_string(),
+        }
+    }
+
+    fn list(&self, field: &Field, v: &ListValue) -> String {
+        let v: Vec<_> = match v {
+            ListValue::Id(v) | ListValue::Int(v) | ListValue::UnixTiemstamp(v) => {
+                v.iter().map(|v| self.label(field, v.to_string())).collect()
+            }
+            ListValue::Num(v) => v.iter().map(|v| v.to_string()).collect(),
+            ListValue::Decimal(v) => v.iter().map(|v| self.decimal(field, v)).collect(),
+            ListValue::Str(v) | ListValue::DateTime(v) => {
+                v.iter().map(|v| self.label(field, v.0.clone())).collect()
+            }
+        };
+        format!("({})", v.join(", "))
+    }
+
+    fn write(&self, f: &mut Formatter<'_>, node: &FilterNode, nested: bool) -> FmtResult {
+        let lang = self.lang;
+        match node {
+            FilterNode::Logical(ope, v) => {
+                if nested {
+                    write!(f, "(")?;
+                }
+                for (i, n) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", lang.logical(ope))?;
+                    }
+                    self.write(f, n, true)?;
+                }
+                if nested {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            FilterNode::Not(_, n) => {
+                write!(f, "{} (", lang.not())?;
+                self.write(f, n, false)?;
+                write!(f, ")")
+            }
+            FilterNode::Nullable(ope, field) => {
+                write!(f, "{} {}", self.field(field), lang.nullable(ope))
+            }
+            FilterNode::Equal(ope, field, v) => {
+                let ope = match ope {
+                    Eq::Eq => "=",
+                    Eq::NotEq => "≠",
+                };
+                write!(f, "{} {} {}", self.field(field), ope, self.value(field, v))
+            }
+            FilterNode::Cmp(ope, field, v) => {
+                let name = self.field(field);
+                write!(f, "{} {} {}", name, cmp(ope), self.value(field, v))
+            }
+            FilterNode::Between(ope, field, lo, hi) => {
+                let (name, lo, hi) = (
+                    self.field(field),
+                    self.value(field, lo),
+                    self.value(field, hi),
+                );
+                match (ope, lang) {
+                    (Between::Between, Lang::Zh) => {
+                        write!(f, "{} 介于 {} 和 {} 之间", name, lo, hi)
+                    }
+                    (Between::Between, Lang::En) => write!(f, "{} between {} and {}", name, lo, hi),
+                    // 半开区间写成两个条件，和 Logical 一样在分组中加括号
+                    _ => {
+                        let (lo_ope, hi_ope) = ope.bounds();
+                        let and = lang.logical(&Logical::And);
+                        let (lo_ope, hi_ope) = (cmp(&lo_ope), cmp(&hi_ope));
+                        let (open, close) = if nested { ("(", ")") } else { ("", "") };
+                        write!(
+                            f,
+                            "{}{} {} {}{}{} {}{}",
+                            open, name, lo_ope, lo, and, hi_ope, hi, close
+                        )
+                    }
+                }
+            }
+            FilterNode::In(ope, field, v) => {
+                let name = self.field(field);
+                write!(f, "{} {} {}", name, lang.in_(ope), self.list(field, v))
+            }
+            FilterNode::Like(ope, field, v) => {
+                write!(f, "{} {} {}", self.field(field), lang.like(ope), v.0)
+            }
+        }
+    }
+}
+
+fn cmp(ope: &Cmp) -> &'static str {
+    match ope {
+        Cmp::Gt => ">",
+        Cmp::GtEq => "≥",
+        Cmp::Lt => "<",
+        Cmp::LtEq => "≤",
+    }
+}
+
+impl Display for FilterText<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.write(f, self.node, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, test_info, Between as B, Cmp::*, Eq::*, Field, FieldInfo};
+    use super::super::{FieldType as FT, FilterNode::*, Like::*, Logical::*, Not::Not as NotOpe};
+    use super::super::{RelativeTime, StrValue, TimeUnit, Value};
+    use super::{EnumLabels, Lang};
+    use std::collections::HashMap;
+
+    fn field(name: &str) -> Field {
+        Field(name.to_string())
+    }
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let info = |name, text, type_| test_info(name, type_, "").with_text(text);
+        field_info_map(vec![
+            info("age", "年龄", FT::Int),
+            info("sex", "性别", FT::Enum("sex".into())),
+            info("name", "姓名", FT::Str),
+            info("reg_time", "注册时间", FT::DateTime),
+        ])
+    }
+
+    fn labels() -> EnumLabels {
+        let sex = [("female", "女"), ("male", "男")];
+        let sex = sex.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+        HashMap::from([("sex".to_string(), sex.collect())])
+    }
+
+    #[test]
+    fn text() {
+        let str = |s: &str| StrValue(s.to_string());
+        #[rustfmt::skip]
+        let root = Logical(And, vec![
+            Cmp(GtEq, field("age"), Value::Int(18)),
+            Logical(Or, vec![
+                Equal(Eq, field("sex"), Value::Str(str("female"))),
+                Like(Contains, field("name"), str("张")),
+            ]),
+        ]);
+        let (infos, labels) = (infos(), labels());
+        let res = "年龄 ≥ 18 且 (性别 = 女 或 姓名 包含 张)";
+        assert_eq!(root.text(&infos, &labels, Lang::Zh).to_string(), res);
+        let res = "年龄 ≥ 18 and (性别 = 女 or 姓名 contains 张)";
+        assert_eq!(root.text(&infos, &labels, Lang::En).to_string(), res);
+    }
+
+    #[test]
+    fn text_not_and_time() {
+        let rt = |offset, trunc| {
+            let unit = TimeUnit::Day;
+            Value::RelativeTime(RelativeTime {
+                offset,
+                unit,
+                trunc,
+            })
+        };
+        let today = Some(TimeUnit::Day);
+        #[rustfmt::skip]
+        let root = Logical(And, vec![
+            Between(B::GtEqLt, field("reg_time"), rt(-30, today), rt(0, today)),
+            Not(NotOpe, Box::new(Nullable(super::super::Nullable::IsNull, field("name")))),
+        ]);
+        let (infos, labels) = (infos(), labels());
+        let res = "(注册时间 ≥ 今天开始 30 天前 且 < 今天开始) 且 非 (姓名 为空)";
+        assert_eq!(root.text(&infos, &labels, Lang::Zh).to_string(), res);
+        let res = "(注册时间 ≥ 30 days before start of today and < start of today) \
+                   and not (姓名 is empty)";
+        assert_eq!(root.text(&infos, &labels, Lang::En).to_string(), res);
+
+        // 不在分组中时不加括号
+        let root = Between(B::GtLtEq, field("age"), Value::Int(1), Value::Int(2));
+        let res = "年龄 > 1 且 ≤ 2";
+        assert_eq!(root.text(&infos, &labels, Lang::Zh).to_string(), res);
+        let root = Logical(
+            Or,
+            vec![root, Nullable(super::super::Nullable::IsNull, field("age"))],
+        );
+        let res = "(年龄 > 1 且 ≤ 2) 或 年龄 为空";
+        assert_eq!(root.text(&infos, &labels, Lang::Zh).to_string(), res);
+
+        let month = rt(0, Some(TimeUnit::Month));
+        let root = Cmp(GtEq, field("reg_time"), month);
+        let res = "注册时间 ≥ 本月开始";
+        assert_eq!(root.text(&infos, &labels, Lang::Zh).to_string(), res);
+        let res = "注册时间 ≥ start of this month";
+        assert_eq!(root.text(&infos, &labels, Lang::En).to_string(), res);
+    }
+}
@@ -0,0 +1,489 @@
+use super::{DataAccessErr, FieldInfo, FieldType, FilterNode, ListValue, StrValue, Value};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Months, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Timelike, Utc,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// 相对时间，例如 "7 天前"、"本月初"
+///
+/// 先按 `trunc` 截断到单位的开始，再加上 `offset` 个 `unit`，
+/// 例如 "上个月初" 为 `{ offset: -1, unit: Month, trunc: Some(Month) }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelativeTime {
+    pub offset: i32,
+    pub unit: TimeUnit,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trunc: Option<TimeUnit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TimeUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    /// 周一为一周的开始
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeUnit {
+    /// 时间运算中的单位缩写，`M` 为月，`m` 为分钟
+    fn abbr(self) -> &'static str {
+        use TimeUnit::*;
+        match self {
+            Second => "s",
+            Minute => "m",
+            Hour => "h",
+            Day => "d",
+            Week => "w",
+            Month => "M",
+            Year => "y",
+        }
+    }
+}
+
+/// 日期运算的写法，用于日志，不是 sql
+impl Display for RelativeTime {
+    /// 按求值顺序，先截断再偏移，例如 "上个月初" 为 `now/M-1M`
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "now")?;
+        if let Some(trunc) = self.trunc {
+            write!(f, "/{}", trunc.abbr())?;
+        }
+        match self.offset {
+            0 => Ok(()),
+            n => write!(f, "{:+}{}", n, self.unit.abbr()),
+        }
+    }
+}
+
+/// 编译过滤条件时使用的时钟和时区
+pub struct Clock {
+    pub now: DateTime<Utc>,
+    pub tz: FixedOffset,
+}
+
+impl Clock {
+    pub fn system(tz: FixedOffset) -> Self {
+        Self {
+            now: Utc::now(),
+            tz,
+        }
+    }
+}
+
+impl RelativeTime {
+    pub fn resolve(&self, clock: &Clock) -> Result<DateTime<FixedOffset>, DataAccessErr> {
+        let now = clock.now.with_timezone(&clock.tz);
+        let time = match self.trunc {
+            Some(unit) => truncate(now, unit).ok_or(DataAccessErr::InvalidOperation)?,
+            None => now,
+        };
+        add(time, self.offset, self.unit).ok_or(DataAccessErr::InvalidOperation)
+    }
+}
+
+fn truncate(time: DateTime<FixedOffset>, unit: TimeUnit) -> Option<DateTime<FixedOffset>> {
+    use TimeUnit::*;
+    let date = time.date_naive();
+    let naive = match unit {
+        Second => return time.with_nanosecond(0),
+        Minute => return time.with_nanosecond(0)?.with_second(0),
+        Hour => return time.with_nanosecond(0)?.with_second(0)?.with_minute(0),
+        Day => date,
+        Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        Month => date.with_day(1)?,
+        Year => date.with_day(1)?.with_month(1)?,
+    };
+    let naive = naive.and_time(NaiveTime::MIN);
+    time.timezone().from_local_datetime(&naive).single()
+}
+
+fn add(time: DateTime<FixedOffset>, offset: i32, unit: TimeUnit) -> Option<DateTime<FixedOffset>> {
+    use TimeUnit::*;
+    let n = offset as i64;
+    // 超过 u32 的月数不能截断，否则会得到一个错误但合法的时间
+    let months = |m: i64| {
+        let abs = Months::new(u32::try_from(m.unsigned_abs()).ok()?);
+        match m < 0 {
+            true => time.checked_sub_months(abs),
+            false => time.checked_add_months(abs),
+        }
+    };
+    match unit {
+        Second => time.checked_add_signed(Duration::seconds(n)),
+        Minute => time.checked_add_signed(Duration::minutes(n)),
+        Hour => time.checked_add_signed(Duration::hours(n)),
+        Day => time.checked_add_signed(Duration::days(n)),
+        Week => time.checked_add_signed(Duration::weeks(n)),
+        Month => months(n),
+        Year => months(n.checked_mul(12)?),
+    }
+}
+
+/// `DateTime` 字段统一的字符串格式，时间都在 [`Clock::tz`] 时区内
+pub const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 解析 ISO-8601 时间，转到时区 `tz`
+///
+/// 支持 `2022-03-16T02:30:00+08:00`、`...Z`、`2022-03-16 02:30:00`、
+/// `2022-03-16T02:30`、`2022-03-16` 等，秒可以有小数；
+/// 没有时区的时间视为 `tz` 中的时间，只有日期时为当天 0 点
+pub fn parse_datetime(s: &str, tz: FixedOffset) -> Result<DateTime<FixedOffset>, DataAccessErr> {
+    let s = s.trim();
+    for fmt in ["%Y-%m-%dT%H:%M:%S%.f%:z", "%Y-%m-%d %H:%M:%S%.f%:z"] {
+        if let Ok(t) = DateTime::parse_from_str(s, fmt) {
+            return Ok(t.with_timezone(&tz));
+        }
+    }
+    if let Some(utc) = s.strip_suffix(['Z', 'z']) {
+        let t = parse_naive(utc).ok_or(DataAccessErr::InvalidFormat)?;
+        return Ok(Utc.from_utc_datetime(&t).with_timezone(&tz));
+    }
+    let t = parse_naive(s).ok_or(DataAccessErr::InvalidFormat)?;
+    tz.from_local_datetime(&t)
+        .single()
+        .ok_or(DataAccessErr::InvalidFormat)
+}
+
+fn parse_naive(s: &str) -> Option<NaiveDateTime> {
+    const FORMATS: [&str; 4] = [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ];
+    let t = FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok());
+    t.or_else(|| {
+        Some(
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()?
+                .and_time(NaiveTime::MIN),
+        )
+    })
+}
+
+fn from_timestamp(ts: u64, tz: FixedOffset) -> Result<DateTime<FixedOffset>, DataAccessErr> {
+    let ts = i64::try_from(ts).map_err(|_| DataAccessErr::TypeErr)?;
+    let t = DateTime::from_timestamp(ts, 0).ok_or(DataAccessErr::TypeErr)?;
+    Ok(t.with_timezone(&tz))
+}
+
+/// 时间类的值对应的时刻，不是时间类的值为 `None`
+pub(super) fn instant(
+    v: &Value,
+    clock: &Clock,
+) -> Result<Option<DateTime<FixedOffset>>, DataAccessErr> {
+    let today = RelativeTime {
+        offset: 0,
+        unit: TimeUnit::Day,
+        trunc: Some(TimeUnit::Day),
+    };
+    Ok(Some(match v {
+        Value::DateTime(s) => parse_datetime(&s.0, clock.tz)?,
+        Value::UnixTiemstamp(ts) => from_timestamp(*ts, clock.tz)?,
+        Value::RelativeTime(rt) => rt.resolve(clock)?,
+        Value::CurrentTime => clock.now.with_timezone(&clock.tz),
+        Value::CurrentDate => today.resolve(clock)?,
+        _ => return Ok(None),
+    }))
+}
+
+/// 时刻按字段类型转为值，`DateTime` 为 [`DATETIME_FORMAT`] 格式，`UnixTiemstamp` 为时间戳
+fn time_value(time: DateTime<FixedOffset>, ft: &FieldType) -> Result<Value, DataAccessErr> {
+    match ft {
+        FieldType::DateTime => {
+            let s = time.format(DATETIME_FORMAT).to_string();
+            Ok(Value::DateTime(StrValue(s)))
+        }
+        FieldType::UnixTiemstamp => {
+            let ts = u64::try_from(time.timestamp()).map_err(|_| DataAccessErr::TypeErr)?;
+            Ok(Value::UnixTiemstamp(ts))
+        }
+        _ => Err(DataAccessErr::TypeErr),
+    }
+}
+
+/// 把相对时间和时间字面量按字段类型转为具体的值，
+/// 时间字符串统一格式，`DateTime` 和 `UnixTiemstamp` 互相转换
+pub(super) fn resolve_value(
+    v: Value,
+    ft: &FieldType,
+    clock: &Clock,
+) -> Result<Value, DataAccessErr> {
+    match v {
+        Value::RelativeTime(_) | Value::DateTime(_) | Value::UnixTiemstamp(_) => {
+            let time = instant(&v, clock)?.ok_or(DataAccessErr::TypeErr)?;
+            time_value(time, ft)
+        }
+        v => Ok(v),
+    }
+}
+
+/// 同 [`resolve_value`]，另外把 `CurrentDate`、`CurrentTime` 也解析为具体时间，
+/// 供不在数据库中执行的后端使用
+pub(super) fn resolve_now(
+    v: &Value,
+    ft: &FieldType,
+    clock: &Clock,
+) -> Result<Value, DataAccessErr> {
+    match instant(v, clock)? {
+        Some(time) => time_value(time, ft),
+        None => Ok(v.clone()),
+    }
+}
+
+/// 同 [`resolve_value`]，时间列表转为字段的类型和格式，其它列表不变
+pub(super) fn resolve_list<'a>(
+    v: &'a ListValue,
+    ft: &FieldType,
+    clock: &Clock,
+) -> Result<Cow<'a, ListValue>, DataAccessErr> {
+    let times: Result<Vec<DateTime<FixedOffset>>, _> = match v {
+        ListValue::DateTime(v) => v.iter().map(|s| parse_datetime(&s.0, clock.tz)).collect(),
+        ListValue::UnixTiemstamp(v) => v.iter().map(|ts| from_timestamp(*ts, clock.tz)).collect(),
+        v => return Ok(Cow::Borrowed(v)),
+    };
+    let times = times?;
+    Ok(Cow::Owned(match ft {
+        FieldType::DateTime => ListValue::DateTime(
+            times
+                .iter()
+                .map(|t| StrValue(t.format(DATETIME_FORMAT).to_string()))
+                .collect(),
+        ),
+        FieldType::UnixTiemstamp => ListValue::UnixTiemstamp(
+            times
+                .iter()
+                .map(|t| u64::try_from(t.timestamp()).map_err(|_| DataAccessErr::TypeErr))
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => return Err(DataAccessErr::TypeErr),
+    }))
+}
+
+impl FilterNode {
+    /// 按 `clock` 把 [`Value::RelativeTime`] 解析为具体时间，
+    /// 字段为 `DateTime` 时转为时区内的时间字符串，`UnixTiemstamp` 时转为时间戳；
+    /// 时间字面量和列表同样转为字段的类型和格式
+    pub fn resolve_time(
+        self,
+        clock: &Clock,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<FilterNode, DataAccessErr> {
+        let ft = |field: &super::Field| {
+            let info = infos.get(&field.0).ok_or(DataAccessErr::UnknownField)?;
+            Ok::<_, DataAccessErr>(&info.type_)
+        };
+        Ok(match self {
+            FilterNode::Logical(ope, v) => {
+                let v = v.into_iter().map(|n| n.resolve_time(clock, infos));
+                FilterNode::Logical(ope, v.collect::<Result<_, _>>()?)
+            }
+            FilterNode::Not(ope, n) => {
+                FilterNode::Not(ope, Box::new(n.resolve_time(clock, infos)?))
+            }
+            FilterNode::Equal(ope, field, v) => {
+                let v = resolve_value(v, ft(&field)?, clock)?;
+                FilterNode::Equal(ope, field, v)
+            }
+            FilterNode::Cmp(ope, field, v) => {
+                let v = resolve_value(v, ft(&field)?, clock)?;
+                FilterNode::Cmp(ope, field, v)
+            }
+            FilterNode::Between(ope, field, lo, hi) => {
+                let lo = resolve_value(lo, ft(&field)?, clock)?;
+                let hi = resolve_value(hi, ft(&field)?, clock)?;
+                FilterNode::Between(ope, field, lo, hi)
+            }
+            FilterNode::In(ope, field, v) => {
+                let v = resolve_list(&v, ft(&field)?, clock)?.into_owned();
+                FilterNode::In(ope, field, v)
+            }
+            node => node,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{field_info_map, test_info, Between, Cmp, Field, FieldType as FT, In};
+    use super::{parse_datetime, Clock, DataAccessErr, FilterNode, ListValue, RelativeTime};
+    use super::{StrValue, TimeUnit::*, Value};
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    fn clock() -> Clock {
+        // 北京时间 2022-03-16 (周三) 02:30:00
+        let now = Utc.with_ymd_and_hms(2022, 3, 15, 18, 30, 0).unwrap();
+        Clock {
+            now,
+            tz: FixedOffset::east_opt(8 * 3600).unwrap(),
+        }
+    }
+
+    fn fmt(rt: RelativeTime) -> String {
+        rt.resolve(&clock()).unwrap().format("%F %T").to_string()
+    }
+
+    #[test]
+    fn resolve() {
+        let rt = |offset, unit, trunc| RelativeTime {
+            offset,
+            unit,
+            trunc,
+        };
+        assert_eq!(fmt(rt(-7, Day, None)), "2022-03-09 02:30:00");
+        assert_eq!(fmt(rt(0, Day, Some(Day))), "2022-03-16 00:00:00");
+        assert_eq!(fmt(rt(0, Day, Some(Week))), "2022-03-14 00:00:00");
+        assert_eq!(fmt(rt(0, Day, Some(Month))), "2022-03-01 00:00:00");
+        assert_eq!(fmt(rt(-1, Month, Some(Month))), "2022-02-01 00:00:00");
+        assert_eq!(fmt(rt(1, Year, Some(Year))), "2023-01-01 00:00:00");
+        assert_eq!(fmt(rt(-2, Hour, Some(Hour))), "2022-03-16 00:00:00");
+
+        // 357913942 年为 2^32 + 8 个月，不能截断为 8 个月
+        for (offset, unit) in [(357913942, Year), (i32::MIN, Year), (i32::MAX, Month)] {
+            assert_eq!(
+                rt(offset, unit, None).resolve(&clock()),
+                Err(DataAccessErr::InvalidOperation)
+            );
+        }
+    }
+
+    #[test]
+    fn display() {
+        let rt = |offset, unit, trunc| RelativeTime {
+            offset,
+            unit,
+            trunc,
+        };
+        assert_eq!(rt(-30, Day, Some(Day)).to_string(), "now/d-30d");
+        assert_eq!(rt(-1, Month, Some(Month)).to_string(), "now/M-1M");
+        assert_eq!(rt(2, Minute, None).to_string(), "now+2m");
+        assert_eq!(rt(0, Week, Some(Week)).to_string(), "now/w");
+        // sql 中没有 `now`，Value 的 Display 不输出没有解析的相对时间
+        let mut s = String::new();
+        let v = Value::RelativeTime(rt(0, Year, None));
+        assert!(std::fmt::write(&mut s, format_args!("{}", v)).is_err());
+    }
+
+    #[test]
+    fn serde() {
+        let json = r#"{"type":"RelativeTime","value":{"offset":-30,"unit":"Day","trunc":"Day"}}"#;
+        let v: Value = serde_json::from_str(json).unwrap();
+        let rt = RelativeTime {
+            offset: -30,
+            unit: Day,
+            trunc: Some(Day),
+        };
+        assert_eq!(v, Value::RelativeTime(rt));
+        assert_eq!(serde_json::to_string(&v).unwrap(), json);
+    }
+
+    #[test]
+    fn resolve_filter() {
+        let info = |name, type_| test_info(name, type_, " Cmp ").with_nullable(false);
+        let infos = field_info_map(vec![
+            info("reg_time", FT::DateTime),
+            info("sub_time", FT::UnixTiemstamp),
+        ]);
+        let last_30_days = || RelativeTime {
+            offset: -30,
+            unit: Day,
+            trunc: Some(Day),
+        };
+        let today = || RelativeTime {
+            offset: 0,
+            unit: Day,
+            trunc: Some(Day),
+        };
+        let root = FilterNode::Between(
+            Between::GtEqLt,
+            Field("reg_time".into()),
+            Value::RelativeTime(last_30_days()),
+            Value::RelativeTime(today()),
+        )
+        .and(FilterNode::Cmp(
+            Cmp::GtEq,
+            Field("sub_time".into()),
+            Value::RelativeTime(today()),
+        ));
+        assert_eq!(root.check(&infos), Ok(()));
+        let root = root.resolve_time(&clock(), &infos).unwrap();
+        let res = FilterNode::Between(
+            Between::GtEqLt,
+            Field("reg_time".into()),
+            Value::DateTime(StrValue("2022-02-14 00:00:00".into())),
+            Value::DateTime(StrValue("2022-03-16 00:00:00".into())),
+        )
+        .and(FilterNode::Cmp(
+            Cmp::GtEq,
+            Field("sub_time".into()),
+            Value::UnixTiemstamp(1647360000),
+        ));
+        assert_eq!(root, res);
+    }
+
+    #[test]
+    fn parse() {
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        let parse = |s| {
+            let t = parse_datetime(s, tz).map(|t| t.format("%F %T%.3f").to_string());
+            t.unwrap_or_default()
+        };
+        assert_eq!(
+            parse("2022-03-16T02:30:00+08:00"),
+            "2022-03-16 02:30:00.000"
+        );
+        assert_eq!(parse("2022-03-15T18:30:00Z"), "2022-03-16 02:30:00.000");
+        assert_eq!(
+            parse("2022-03-15 18:30:00.5+00:00"),
+            "2022-03-16 02:30:00.500"
+        );
+        assert_eq!(parse(" 2022-03-16 02:30:00 "), "2022-03-16 02:30:00.000");
+        assert_eq!(parse("2022-03-16T02:30"), "2022-03-16 02:30:00.000");
+        assert_eq!(parse("2022-03-16"), "2022-03-16 00:00:00.000");
+        for s in ["2022/03/16", "2022-02-30", "16 Mar 2022", ""] {
+            assert_eq!(parse_datetime(s, tz), Err(DataAccessErr::InvalidFormat));
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        let info = |name, type_| test_info(name, type_, " Cmp In ").with_nullable(false);
+        let infos = field_info_map(vec![
+            info("reg_time", FT::DateTime),
+            info("sub_time", FT::UnixTiemstamp),
+        ]);
+        let time = |s: &str| Value::DateTime(StrValue(s.into()));
+        let cmp = |field: &str, v| FilterNode::Cmp(Cmp::GtEq, Field(field.into()), v);
+        let resolve = |node: FilterNode| node.resolve_time(&clock(), &infos).unwrap();
+        assert_eq!(
+            resolve(cmp("reg_time", time("2022-03-15T18:30:00Z"))),
+            cmp("reg_time", time("2022-03-16 02:30:00"))
+        );
+        // 客户端传错类型时按字段的类型转换
+        assert_eq!(
+            resolve(cmp("reg_time", Value::UnixTiemstamp(1647360000))),
+            cmp("reg_time", time("2022-03-16 00:00:00"))
+        );
+        assert_eq!(
+            resolve(cmp("sub_time", time("2022-03-16"))),
+            cmp("sub_time", Value::UnixTiemstamp(1647360000))
+        );
+        let list = ListValue::DateTime(vec![StrValue("2022-03-16".into())]);
+        let node = FilterNode::In(In::In, Field("sub_time".into()), list);
+        let list = ListValue::UnixTiemstamp(vec![1647360000]);
+        assert_eq!(
+            resolve(node),
+            FilterNode::In(In::In, Field("sub_time".into()), list)
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use super::{
+    AggFn, Aggregate, Between, Cmp, Decimal, Direction, Eq, Field, FieldInfo, FieldType,
+    FilterNode, In, Like, ListValue, Logical, Not, Nullable, Nulls, RelativeTime, SortItem,
+    StrValue, TimeUnit, Value, VersionedFilter,
+};
+use serde::Serialize;
+
+/// 结构体的 ts 接口，字段取自穷尽的解构模式，Rust 结构体增删、改名字段时这里会编译不过
+///
+/// `optional` 中的字段对应 `#[serde(default)]`，生成 `field?: type`
+macro_rules! ts_interface {
+    (
+        $ty:ident { $($field:ident: $ts:expr),* $(,)? }
+        $(optional { $($opt:ident: $opt_ts:expr),* $(,)? })?
+    ) => {{
+        #[allow(dead_code)]
+        fn exhaustive(v: &$ty) {
+            let $ty { $($field: _,)* $($($opt: _,)*)? } = v;
+        }
+        let mut ts = format!("export interface {} {{\n", stringify!($ty));
+        $(ts.push_str(&format!("    {}: {},\n", stringify!($field), $ts));)*
+        $($(ts.push_str(&format!("    {}?: {},\n", stringify!($opt), $opt_ts));)*)?
+        ts.push('}');
+        ts
+    }};
+}
+pub(crate) use ts_interface;
+
+/// 序列化后的名字，和 serde 保持一致
+fn name<T: Serialize>(v: &T) -> String {
+    serde_json::to_value(v).unwrap().to_string()
+}
+
+fn union<T: Serialize>(v: &[T]) -> String {
+    let v: Vec<_> = v.iter().map(name).collect();
+    v.join(" | ")
+}
+
+fn tag<T: Serialize>(v: &T) -> String {
+    serde_json::to_value(v).unwrap()["type"].to_string()
+}
+
+/// `Value` 每个变体的 ts 类型，新增变体时这里会编译不过
+fn value_ts(v: &Value) -> Option<&'static str> {
+    match v {
+        Value::Id(_) | Value::Int(_) | Value::Num(_) | Value::UnixTiemstamp(_) => Some("number"),
+        Value::Str(_) | Value::DateTime(_) | Value::Field(_) => Some("string"),
+        Value::Decimal(_) => Some("string"),
+        Value::RelativeTime(_) => Some("RelativeTime"),
+        Value::CurrentUserId | Value::CurrentDate | Value::CurrentTime => None,
+    }
+}
+
+fn list_ts(v: &ListValue) -> &'static str {
+    match v {
+        ListValue::Id(_) | ListValue::Int(_) | ListValue::Num(_) => "number[]",
+        ListValue::UnixTiemstamp(_) => "number[]",
+        ListValue::Str(_) | ListValue::DateTime(_) => "string[]",
+        ListValue::Decimal(_) => "string[]",
+    }
+}
+
+fn field_type_ts(v: &FieldType) -> String {
+    match v {
+        FieldType::Enum(_) => "{ Enum: string }".to_string(),
+        FieldType::Decimal { .. } => "{ Decimal: { scale: number } }".to_string(),
+        FieldType::Str
+        | FieldType::Int
+        | FieldType::Num
+        | FieldType::DateTime
+        | FieldType::UnixTiemstamp
+        | FieldType::Id => name(v),
+    }
+}
+
+/// `FilterNode` 每个变体对应的 ts 元组，新增变体时这里会编译不过
+fn node_ts(v: &FilterNode) -> &'static str {
+    match v {
+        FilterNode::Logical(..) => "[Logical, FilterNode[]]",
+        FilterNode::Not(..) => "[Not, FilterNode]",
+        FilterNode::Nullable(..) => "[Nullable, FieldName]",
+        FilterNode::Equal(..) => "[Equal, FieldName, Value]",
+        FilterNode::Cmp(..) => "[Cmp, FieldName, Value]",
+        FilterNode::Between(..) => "[Between, FieldName, Value, Value]",
+        FilterNode::In(..) => "[In, FieldName, ListValue]",
+        FilterNode::Like(..) => "[Like, FieldName, string]",
+    }
+}
+
+/// 生成过滤条件相关类型的 TypeScript 定义
+pub fn filter_dts() -> String {
+    let s = || StrValue(String::new());
+    let field = || Field(String::new());
+    let rt = || RelativeTime {
+        offset: 0,
+        unit: TimeUnit::Day,
+        trunc: None,
+    };
+    #[rustfmt::skip]
+    let values = [
+        Value::Id(0), Value::Int(0), Value::Num(0.0), Value::Decimal(Decimal::from(0)),
+        Value::Str(s()), Value::DateTime(s()), Value::UnixTiemstamp(0), Value::CurrentUserId,
+        Value::CurrentDate, Value::CurrentTime, Value::RelativeTime(rt()), Value::Field(field()),
+    ];
+    #[rustfmt::skip]
+    let lists = [
+        ListValue::Id(vec![]), ListValue::Int(vec![]), ListValue::Num(vec![]),
+        ListValue::Decimal(vec![]), ListValue::Str(vec![]), ListValue::DateTime(vec![]),
+        ListValue::UnixTiemstamp(vec![]),
+    ];
+    #[rustfmt::skip]
+    let field_types = [
+        FieldType::Str, FieldType::Int, FieldType::Num, FieldType::Decimal { scale: 0 },
+        FieldType::DateTime, FieldType::UnixTiemstamp, FieldType::Id,
+        FieldType::Enum(String::new()),
+    ];
+    #[rustfmt::skip]
+    let nodes = [
+        FilterNode::Logical(Logical::And, vec![]),
+        FilterNode::Not(Not::Not, Box::new(FilterNode::Logical(Logical::And, vec![]))),
+        FilterNode::Nullable(Nullable::IsNull, field()),
+        FilterNode::Equal(Eq::Eq, field(), Value::CurrentUserId),
+        FilterNode::Cmp(Cmp::Gt, field(), Value::CurrentUserId),
+        FilterNode::Between(Between::Between, field(), Value::CurrentUserId, Value::CurrentUserId),
+        FilterNode::In(In::In, field(), ListValue::Id(vec![])),
+        FilterNode::Like(Like::Contains, field(), s()),
+    ];
+    #[rustfmt::skip]
+    let time_units = [
+        TimeUnit::Second, TimeUnit::Minute, TimeUnit::Hour, TimeUnit::Day,
+        TimeUnit::Week, TimeUnit::Month, TimeUnit::Year,
+    ];
+
+    let mut ts = String::new();
+    let mut line = |s: String| {
+        ts.push_str(&s);
+        ts.push('\n');
+    };
+    line("export type FieldName = string".into());
+    line(format!(
+        "export type Logical = {}",
+        union(&[Logical::And, Logical::Or])
+    ));
+    line(format!("export type Not = {}", union(&[Not::Not])));
+    line(format!(
+        "export type Nullable = {}",
+        union(&[Nullable::IsNull, Nullable::IsNotNull])
+    ));
+    line(format!(
+        "export type Equal = {}",
+        union(&[Eq::Eq, Eq::NotEq])
+    ));
+    line(format!(
+        "export type Cmp = {}",
+        union(&[Cmp::Gt, Cmp::GtEq, Cmp::Lt, Cmp::LtEq])
+    ));
+    line(format!(
+        "export type Between = {}",
+        union(&[Between::Between, Between::GtEqLt, Between::GtLtEq])
+    ));
+    line(format!("export type In = {}", union(&[In::In, In::NotIn])));
+    line(format!(
+        "export type Like = {}",
+        union(&[Like::StartWith, Like::Contains, Like::EndWith])
+    ));
+    line(format!("export type TimeUnit = {}", union(&time_units)));
+    line(ts_interface!(RelativeTime {
+        offset: "number",
+        unit: "TimeUnit",
+    } optional {
+        trunc: "TimeUnit",
+    }));
+    line(String::new());
+
+    line("export type Value =".into());
+    for v in values.iter() {
+        match value_ts(v) {
+            Some(t) => line(format!("    | {{ type: {}, value: {} }}", tag(v), t)),
+            None => line(format!("    | {{ type: {} }}", tag(v))),
+        }
+    }
+    line("export type ListValue =".into());
+    for v in lists.iter() {
+        line(format!(
+            "    | {{ type: {}, value: {} }}",
+            tag(v),
+            list_ts(v)
+        ));
+    }
+    line("export type FieldType =".into());
+    for v in field_types.iter() {
+        line(format!("    | {}", field_type_ts(v)));
+    }
+    line("export type FilterNode =".into());
+    for v in nodes.iter() {
+        line(format!("    | {}", node_ts(v)));
+    }
+    line(String::new());
+
+    line(ts_interface!(FieldInfo {
+        name: "FieldName",
+        text: "string",
+        nullable: "boolean",
+        type_: "FieldType",
+        operation: "string",
+    } optional {
+        sortable: "boolean",
+        aggregatable: "boolean",
+    }));
+    line(ts_interface!(VersionedFilter {
+        version: "number",
+        filter: "FilterNode",
+    }));
+    line(String::new());
+
+    line(format!(
+        "export type Direction = {}",
+        union(&[Direction::Asc, Direction::Desc])
+    ));
+    line(format!(
+        "export type Nulls = {}",
+        union(&[Nulls::First, Nulls::Last])
+    ));
+    line(ts_interface!(SortItem {
+        field: "FieldName",
+    } optional {
+        direction: "Direction",
+        nulls: "Nulls",
+    }));
+    line("export type SortSpec = SortItem[]".into());
+    line(format!(
+        "export type AggFn = {}",
+        union(&[AggFn::Count, AggFn::Sum, AggFn::Avg, AggFn::Min, AggFn::Max])
+    ));
+    line(ts_interface!(Aggregate {
+        func: "AggFn",
+        alias: "string",
+    } optional {
+        field: "FieldName",
+    }));
+    ts
+}
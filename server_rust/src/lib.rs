@@ -4,6 +4,8 @@
 pub mod data_access;
 pub mod data_access1;
 pub mod permission;
+pub mod ts;
+pub mod view;
 
 use permission::{ApiFlag, FnDisplay, FnFlag, Role, RoleFn, User, UserRole};
 
@@ -107,6 +109,12 @@ pub fn get_user_permission(user_id: u32) -> HashMap {
     map
 }
 
+/// 用户拥有的角色 id
+pub fn get_user_roles(user_id: u32) -> Vec<u32> {
+    let v = user_roles.iter().filter(|&ur| ur.user_id == user_id);
+    v.map(|ur| ur.role_id).collect()
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -30,7 +30,7 @@ impl FnFlag {
             parent_id,
             key,
             text,
-            display: display,
+            display,
         }
     }
 }
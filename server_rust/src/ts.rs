@@ -0,0 +1,100 @@
+use crate::data_access1::{filter_dts, ts_interface};
+use crate::permission::{FnDisplay, FnFlag};
+use crate::view::{SavedView, Visibility};
+use crate::{api_flags, fn_flags};
+
+/// 生成的文件，前端直接引用
+pub const DTS_PATH: &str = "../web_vue/src/types/server.d.ts";
+
+fn keys<'a>(v: impl Iterator<Item = &'a str>) -> String {
+    let mut seen = vec![];
+    v.filter(|k| !k.is_empty()).for_each(|k| {
+        if !seen.contains(&k) {
+            seen.push(k)
+        }
+    });
+    let v: Vec<_> = seen.iter().map(|k| format!("{:?}", k)).collect();
+    v.join(" | ")
+}
+
+/// `FnDisplay` 每个变体的 ts 类型，新增变体时这里会编译不过
+fn fn_display_ts(v: &FnDisplay) -> String {
+    let name = match v {
+        FnDisplay::Show => "Show",
+        FnDisplay::Disable => "Disable",
+    };
+    format!("\"{}\"", name)
+}
+
+/// `Visibility` 每个变体的 ts 类型，新增变体时这里会编译不过
+fn visibility_ts(v: &Visibility) -> String {
+    match v {
+        Visibility::Private | Visibility::Global => serde_json::to_string(v).unwrap(),
+        Visibility::Role(_) => "{ Role: number }".to_string(),
+    }
+}
+
+fn union(v: impl Iterator<Item = String>) -> String {
+    v.collect::<Vec<_>>().join(" | ")
+}
+
+/// 生成权限和过滤条件的 TypeScript 定义，`cargo run --bin gen_ts` 写入 [`DTS_PATH`]
+pub fn dts() -> String {
+    let mut ts = String::new();
+    ts.push_str("// 由 server_rust `cargo run --bin gen_ts` 生成，不要手动修改\n\n");
+    ts.push_str(&filter_dts());
+    ts.push('\n');
+    let displays = [FnDisplay::Show, FnDisplay::Disable];
+    let displays = union(displays.iter().map(fn_display_ts));
+    ts.push_str(&format!("export type FnDisplay = {}\n", displays));
+    ts.push_str(&ts_interface!(FnFlag {
+        id: "number",
+        parent_id: "number",
+        seq: "number",
+        flag: "number",
+        key: "FnFlagKey | \"\"",
+        text: "string",
+        display: "FnDisplay",
+    }));
+    ts.push('\n');
+    let fn_keys = keys(fn_flags.iter().map(|f| f.key));
+    ts.push_str(&format!("export type FnFlagKey = {}\n", fn_keys));
+    let apis = keys(api_flags.iter().map(|f| f.api));
+    ts.push_str(&format!("export type ApiKey = {}\n", apis));
+    ts.push_str("/** 用户权限，seq => 功能标记位 */\n");
+    ts.push_str("export type UserPermission = Record<number, number>\n");
+    ts.push('\n');
+    let visibilities = [Visibility::Private, Visibility::Global, Visibility::Role(0)];
+    let visibilities = union(visibilities.iter().map(visibility_ts));
+    ts.push_str(&format!("export type Visibility = {}\n", visibilities));
+    ts.push_str(&ts_interface!(SavedView {
+        id: "number",
+        page: "string",
+        name: "string",
+        owner_id: "number",
+        visibility: "Visibility",
+        filter: "VersionedFilter",
+        sort: "SortSpec",
+        revision: "number",
+    }));
+    ts.push('\n');
+    ts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dts, DTS_PATH};
+    use std::path::Path;
+
+    /// Rust 类型改动后需要重新生成，前端编译会检查到不兼容的地方
+    #[test]
+    fn dts_up_to_date() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(DTS_PATH);
+        let file = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            file == dts(),
+            "{} 已过期，运行 cargo run --bin gen_ts",
+            DTS_PATH
+        );
+    }
+}
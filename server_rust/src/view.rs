@@ -0,0 +1,428 @@
+//! 保存的视图：命名的过滤条件和排序，前端 `SqlFilter` 组件直接加载使用
+
+use crate::data_access1::{DataAccessErr, FieldInfo, FilterLimits, SortSpec, VersionedFilter};
+use crate::get_user_roles;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// 谁可以看到视图，修改和删除只有创建者可以
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Visibility {
+    Private,
+    /// 拥有该角色的用户，创建者自己也需要拥有这个角色
+    Role(u32),
+    Global,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedView {
+    pub id: u64,
+    /// 视图所属的列表页面，例如 `wx_user`
+    pub page: String,
+    pub name: String,
+    pub owner_id: u32,
+    pub visibility: Visibility,
+    /// 读取时兼容旧格式，见 [`VersionedFilter::from_value`]
+    #[serde(deserialize_with = "de_filter")]
+    pub filter: VersionedFilter,
+    #[serde(default)]
+    pub sort: SortSpec,
+    /// 每次修改加 1，修改时需要带上读取到的版本号，防止覆盖别人的修改
+    pub revision: u32,
+}
+
+fn de_filter<'de, D: Deserializer<'de>>(d: D) -> Result<VersionedFilter, D::Error> {
+    let json = serde_json::Value::deserialize(d)?;
+    VersionedFilter::from_value(json).map_err(|_| serde::de::Error::custom("invalid filter"))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ViewErr {
+    NotFound,
+    /// 没有权限查看、修改或者设置为该可见范围
+    Forbidden,
+    /// 版本号不一致，视图已经被修改
+    Conflict,
+    /// 同一个用户在同一个页面下的视图名字重复
+    DuplicateName,
+    Filter(DataAccessErr),
+    Io(String),
+}
+
+impl From<DataAccessErr> for ViewErr {
+    fn from(err: DataAccessErr) -> Self {
+        ViewErr::Filter(err)
+    }
+}
+
+/// 视图的存储，不做权限检查
+pub trait ViewStore {
+    fn get(&self, id: u64) -> Result<Option<SavedView>, ViewErr>;
+    fn list(&self, page: &str) -> Result<Vec<SavedView>, ViewErr>;
+    /// 分配 id 后保存，返回 id
+    fn insert(&mut self, view: SavedView) -> Result<u64, ViewErr>;
+    fn update(&mut self, view: SavedView) -> Result<(), ViewErr>;
+    fn delete(&mut self, id: u64) -> Result<(), ViewErr>;
+}
+
+#[derive(Debug)]
+pub struct MemoryViewStore {
+    views: BTreeMap<u64, SavedView>,
+    /// 下一个分配的 id，只增不减，删除后 id 也不会被重新使用
+    next_id: u64,
+}
+
+impl MemoryViewStore {
+    /// `next_id` 小于已有的 id 时从最大的 id 之后开始
+    fn new(views: Vec<SavedView>, next_id: u64) -> Self {
+        let after_max = views.iter().map(|v| v.id + 1).max().unwrap_or(1);
+        Self {
+            views: views.into_iter().map(|v| (v.id, v)).collect(),
+            next_id: next_id.max(after_max),
+        }
+    }
+}
+
+impl Default for MemoryViewStore {
+    fn default() -> Self {
+        Self::new(vec![], 1)
+    }
+}
+
+impl ViewStore for MemoryViewStore {
+    fn get(&self, id: u64) -> Result<Option<SavedView>, ViewErr> {
+        Ok(self.views.get(&id).cloned())
+    }
+
+    fn list(&self, page: &str) -> Result<Vec<SavedView>, ViewErr> {
+        let v = self.views.values().filter(|v| v.page == page);
+        Ok(v.cloned().collect())
+    }
+
+    fn insert(&mut self, mut view: SavedView) -> Result<u64, ViewErr> {
+        let id = self.next_id;
+        self.next_id += 1;
+        view.id = id;
+        self.views.insert(id, view);
+        Ok(id)
+    }
+
+    fn update(&mut self, view: SavedView) -> Result<(), ViewErr> {
+        let old = self.views.get_mut(&view.id).ok_or(ViewErr::NotFound)?;
+        *old = view;
+        Ok(())
+    }
+
+    fn delete(&mut self, id: u64) -> Result<(), ViewErr> {
+        self.views.remove(&id).map(|_| ()).ok_or(ViewErr::NotFound)
+    }
+}
+
+/// 保存为一个 json 文件，每次修改后整个文件重写
+#[derive(Debug)]
+pub struct FileViewStore {
+    path: PathBuf,
+    mem: MemoryViewStore,
+}
+
+/// 文件内容，同时保存 `next_id`，重新打开后也不会使用已删除视图的 id
+#[derive(Serialize, Deserialize)]
+struct ViewFile<V> {
+    next_id: u64,
+    views: Vec<V>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyViewFile {
+    Current(ViewFile<SavedView>),
+    /// 早先只保存视图的数组
+    Legacy(Vec<SavedView>),
+}
+
+fn io_err(err: impl std::fmt::Display) -> ViewErr {
+    ViewErr::Io(err.to_string())
+}
+
+impl FileViewStore {
+    /// 文件不存在时为空
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ViewErr> {
+        let path = path.as_ref().to_path_buf();
+        let file = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).map_err(io_err)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => AnyViewFile::Legacy(vec![]),
+            Err(err) => return Err(io_err(err)),
+        };
+        let mem = match file {
+            AnyViewFile::Current(f) => MemoryViewStore::new(f.views, f.next_id),
+            AnyViewFile::Legacy(views) => MemoryViewStore::new(views, 1),
+        };
+        Ok(Self { path, mem })
+    }
+
+    /// 先写临时文件再替换，避免写到一半时文件损坏
+    fn save(&self) -> Result<(), ViewErr> {
+        let file = ViewFile {
+            next_id: self.mem.next_id,
+            views: self.mem.views.values().collect(),
+        };
+        let json = serde_json::to_string_pretty(&file).map_err(io_err)?;
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, json).map_err(io_err)?;
+        std::fs::rename(&tmp, &self.path).map_err(io_err)
+    }
+}
+
+impl ViewStore for FileViewStore {
+    fn get(&self, id: u64) -> Result<Option<SavedView>, ViewErr> {
+        self.mem.get(id)
+    }
+
+    fn list(&self, page: &str) -> Result<Vec<SavedView>, ViewErr> {
+        self.mem.list(page)
+    }
+
+    fn insert(&mut self, view: SavedView) -> Result<u64, ViewErr> {
+        let id = self.mem.insert(view)?;
+        self.save()?;
+        Ok(id)
+    }
+
+    fn update(&mut self, view: SavedView) -> Result<(), ViewErr> {
+        self.mem.update(view)?;
+        self.save()
+    }
+
+    fn delete(&mut self, id: u64) -> Result<(), ViewErr> {
+        self.mem.delete(id)?;
+        self.save()
+    }
+}
+
+/// 带权限检查的视图操作
+pub struct Views<S: ViewStore> {
+    pub store: S,
+}
+
+fn can_read(view: &SavedView, user_id: u32) -> bool {
+    match view.visibility {
+        Visibility::Global => true,
+        Visibility::Private => view.owner_id == user_id,
+        Visibility::Role(role_id) => {
+            view.owner_id == user_id || get_user_roles(user_id).contains(&role_id)
+        }
+    }
+}
+
+impl<S: ViewStore> Views<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// 用户在页面下可以看到的视图
+    pub fn list(&self, user_id: u32, page: &str) -> Result<Vec<SavedView>, ViewErr> {
+        let mut v = self.store.list(page)?;
+        v.retain(|view| can_read(view, user_id));
+        Ok(v)
+    }
+
+    pub fn get(&self, user_id: u32, id: u64) -> Result<SavedView, ViewErr> {
+        let view = self.store.get(id)?.ok_or(ViewErr::NotFound)?;
+        match can_read(&view, user_id) {
+            true => Ok(view),
+            false => Err(ViewErr::NotFound),
+        }
+    }
+
+    /// 检查可见范围、名字和过滤条件，`infos` 为用户在该页面有权限的字段
+    fn check(
+        &self,
+        user_id: u32,
+        view: &SavedView,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<(), ViewErr> {
+        if let Visibility::Role(role_id) = view.visibility {
+            if !get_user_roles(user_id).contains(&role_id) {
+                return Err(ViewErr::Forbidden);
+            }
+        }
+        let same_name = self
+            .store
+            .list(&view.page)?
+            .into_iter()
+            .any(|v| v.id != view.id && v.owner_id == view.owner_id && v.name == view.name);
+        if same_name {
+            return Err(ViewErr::DuplicateName);
+        }
+        view.filter.filter.check_limits(&FilterLimits::default())?;
+        view.filter.filter.check(infos)?;
+        view.sort.check(infos)?;
+        Ok(())
+    }
+
+    /// 保存新的视图，创建者为 `user_id`，忽略传入的 id、owner_id 和 revision
+    pub fn create(
+        &mut self,
+        user_id: u32,
+        mut view: SavedView,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<SavedView, ViewErr> {
+        view.id = 0;
+        view.owner_id = user_id;
+        view.revision = 1;
+        self.check(user_id, &view, infos)?;
+        view.id = self.store.insert(view.clone())?;
+        Ok(view)
+    }
+
+    /// 只有创建者可以修改，`view.revision` 需要和保存的一致
+    pub fn update(
+        &mut self,
+        user_id: u32,
+        mut view: SavedView,
+        infos: &HashMap<String, FieldInfo>,
+    ) -> Result<SavedView, ViewErr> {
+        let old = self.get(user_id, view.id)?;
+        if old.owner_id != user_id {
+            return Err(ViewErr::Forbidden);
+        }
+        if old.revision != view.revision {
+            return Err(ViewErr::Conflict);
+        }
+        view.owner_id = old.owner_id;
+        view.page = old.page;
+        view.revision += 1;
+        self.check(user_id, &view, infos)?;
+        self.store.update(view.clone())?;
+        Ok(view)
+    }
+
+    pub fn delete(&mut self, user_id: u32, id: u64) -> Result<(), ViewErr> {
+        let old = self.get(user_id, id)?;
+        if old.owner_id != user_id {
+            return Err(ViewErr::Forbidden);
+        }
+        self.store.delete(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileViewStore, MemoryViewStore, SavedView, ViewErr, ViewStore, Views, Visibility};
+    use crate::data_access1::{field_info_map, test_info, FieldInfo, FieldType, FilterNode};
+    use crate::data_access1::{SortItem, SortSpec, VersionedFilter};
+    use std::collections::HashMap;
+
+    fn infos() -> HashMap<String, FieldInfo> {
+        let nickname = test_info("nickname", FieldType::Str, " Equal Like ").with_text("昵称");
+        field_info_map(vec![nickname.with_sortable(true)])
+    }
+
+    fn view(name: &str, visibility: Visibility) -> SavedView {
+        let filter = FilterNode::parse("nickname like '张%'", &infos()).unwrap();
+        SavedView {
+            id: 0,
+            page: "wx_user".into(),
+            name: name.into(),
+            owner_id: 0,
+            visibility,
+            filter: VersionedFilter::new(filter),
+            sort: SortSpec(vec![SortItem::asc("nickname")]),
+            revision: 0,
+        }
+    }
+
+    #[test]
+    fn visibility() {
+        let mut views = Views::new(MemoryViewStore::default());
+        let infos = &infos();
+        // 用户 1 拥有角色 2、5，用户 3 拥有角色 2、3，用户 2 只有角色 1
+        views
+            .create(1, view("mine", Visibility::Private), infos)
+            .unwrap();
+        views
+            .create(1, view("role", Visibility::Role(2)), infos)
+            .unwrap();
+        views
+            .create(2, view("all", Visibility::Global), infos)
+            .unwrap();
+        let names = |user_id| {
+            let v = views.list(user_id, "wx_user").unwrap();
+            v.into_iter().map(|v| v.name).collect::<Vec<_>>()
+        };
+        assert_eq!(names(1), ["mine", "role", "all"]);
+        assert_eq!(names(3), ["role", "all"]);
+        assert_eq!(names(2), ["all"]);
+        assert_eq!(views.get(2, 1), Err(ViewErr::NotFound));
+        // 不能分享给自己没有的角色
+        assert_eq!(
+            views.create(2, view("x", Visibility::Role(2)), infos),
+            Err(ViewErr::Forbidden)
+        );
+    }
+
+    #[test]
+    fn update() {
+        let mut views = Views::new(MemoryViewStore::default());
+        let infos = &infos();
+        let v = views
+            .create(1, view("a", Visibility::Global), infos)
+            .unwrap();
+        assert_eq!(
+            views.create(1, view("a", Visibility::Private), infos),
+            Err(ViewErr::DuplicateName)
+        );
+        let mut changed = v.clone();
+        changed.name = "b".into();
+        let changed = views.update(1, changed, infos).unwrap();
+        assert_eq!(changed.revision, 2);
+        // 用旧版本修改
+        assert_eq!(views.update(1, v.clone(), infos), Err(ViewErr::Conflict));
+        assert_eq!(
+            views.update(3, changed.clone(), infos),
+            Err(ViewErr::Forbidden)
+        );
+        assert_eq!(views.delete(3, v.id), Err(ViewErr::Forbidden));
+        views.delete(1, v.id).unwrap();
+        assert_eq!(views.get(1, v.id), Err(ViewErr::NotFound));
+        // 删除最新的视图后 id 也不会被重新使用
+        let next = views.create(1, view("a", Visibility::Global), infos);
+        assert_eq!(next.unwrap().id, v.id + 1);
+
+        let mut bad = view("c", Visibility::Private);
+        bad.sort = SortSpec(vec![SortItem::asc("age")]);
+        assert_eq!(
+            views.create(1, bad, infos),
+            Err(ViewErr::Filter(
+                crate::data_access1::DataAccessErr::UnknownField
+            ))
+        );
+    }
+
+    #[test]
+    fn file_store() {
+        let path = std::env::temp_dir().join(format!("views_{}.json", std::process::id()));
+        let mut store = FileViewStore::open(&path).unwrap();
+        let mut v = view("a", Visibility::Global);
+        v.id = store.insert(v.clone()).unwrap();
+        let mut store = FileViewStore::open(&path).unwrap();
+        assert_eq!(store.get(v.id).unwrap(), Some(v.clone()));
+        store.delete(v.id).unwrap();
+        let mut store = FileViewStore::open(&path).unwrap();
+        assert_eq!(store.insert(v.clone()).unwrap(), v.id + 1);
+
+        // 旧格式的过滤条件在读取时转换
+        let legacy = r#"[{ "id": 1, "page": "wx_user", "name": "a", "owner_id": 1,
+            "visibility": "Global", "revision": 1,
+            "filter": { "eq": ["nickname", { "str": "a" }] } }]"#;
+        std::fs::write(&path, legacy).unwrap();
+        let store = FileViewStore::open(&path).unwrap();
+        let view = store.get(1).unwrap().unwrap();
+        assert_eq!(
+            view.filter.filter,
+            FilterNode::parse("nickname = 'a'", &infos()).unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,3 +1,9 @@
+import type {
+    Cmp, Equal, FieldInfo as ServerFieldInfo, FieldName, FieldType,
+    FilterNode, In, Like, ListValue, Logical, Nullable, Value,
+} from "../../types/server"
+
+export type { FieldName, FieldType, ListValue, Value }
 
 // export type FilterNode =
 //     | { and: FilterNode[] }
@@ -16,12 +22,12 @@
 
 export type FilterNode2 = LogicalNode | OperationNode
 
-export type LogicalNode = [keyof typeof logicalObj, FilterNode2[]]
-export type NullableNode = [keyof typeof nullableObj, FieldName]
-export type EqualNode = [keyof typeof equalObj, FieldName, Value]
-export type CmpNode = [keyof typeof cmpObj, FieldName, Value]
-export type LikeNode = [keyof typeof likeObj, FieldName, string]
-export type InNode = [keyof typeof inObj, FieldName, ListValue]
+export type LogicalNode = [Logical, FilterNode2[]]
+export type NullableNode = Extract<FilterNode, [Nullable, ...unknown[]]>
+export type EqualNode = Extract<FilterNode, [Equal, ...unknown[]]>
+export type CmpNode = Extract<FilterNode, [Cmp, ...unknown[]]>
+export type LikeNode = Extract<FilterNode, [Like, ...unknown[]]>
+export type InNode = Extract<FilterNode, [In, ...unknown[]]>
 export type OperationNode = 
     | NullableNode
     | EqualNode
@@ -29,6 +35,9 @@ export type OperationNode =
     | InNode
     | LikeNode
 
+// 编辑器里的节点要能直接发给服务端，和 server.d.ts 不一致时这里编译不过
+const _toServerNode: (node: FilterNode2) => FilterNode = node => node
+
 export type FilterNode3 = LogicalNodeCom | OperationNode
 
 // export type LogicalNode3 = [keyof typeof logicalObj, { n: FilterNode3 }[]]
@@ -78,12 +87,12 @@ export function toFilterNode(node: FilterNode3): FilterNode2 {
 // export type InKey = keyof typeof inObj
 // export const eqKey = { eq: "等于", notEq: "不等于" } as const
 // export const cmpKey = { ...eqKey, gt: "大于", gte: "大于等于", lt: "小于", lte: "小于等于" } as const
-export const logicalObj = { "And": "并且", "Or": "或者" } as const
-export const nullableObj = { IsNull: "空的", IsNotNull: "非空" } as const
-export const equalObj = { Eq: " = ", NotEq: "≠" } as const
-export const cmpObj = { Gt: ">", Gte: "≥", Lt: "<", Lte: "≤" } as const
-export const inObj = { In: "属于", NotIn: "不属于" } as const
-export const likeObj = { StartWith: "开始为", Contains: "包含", EndWith: "结束为" } as const
+export const logicalObj: Record<Logical, string> = { "And": "并且", "Or": "或者" }
+export const nullableObj: Record<Nullable, string> = { IsNull: "空的", IsNotNull: "非空" }
+export const equalObj: Record<Equal, string> = { Eq: " = ", NotEq: "≠" }
+export const cmpObj: Record<Cmp, string> = { Gt: ">", GtEq: "≥", Lt: "<", LtEq: "≤" }
+export const inObj: Record<In, string> = { In: "属于", NotIn: "不属于" }
+export const likeObj: Record<Like, string> = { StartWith: "开始为", Contains: "包含", EndWith: "结束为" }
 
 
 type ObjStr = Record<string, string>
@@ -96,22 +105,17 @@ export function createOperationNode(field: FieldInfo, operationObjs: ObjStr[]):
     const defOperator = Object.keys((operationObjs[0] || {}))[0] as OperationNode[0]
     if (!defOperator) { throw "operationObjs is empty" }
 
-    const type = typeof field.type == "object" ? "Str" : field.type
     if (defOperator in nullableObj) {
         return [defOperator as NullableNode[0], field.name]
     } else if (defOperator in inObj) {
-        return [defOperator as InNode[0], field.name, { type, value: [] }]
+        return [defOperator as InNode[0], field.name, emptyList(field.type_)]
     } else if (defOperator in likeObj) {
-        return [defOperator as LikeNode[0], field.name, "" as const]
+        return [defOperator as LikeNode[0], field.name, ""]
     } else {
-        const defValue = typeof field.type == "object" 
-            ? { type: "Str" as const, value: Object.keys(field.type)[0] }
-            : defalueValue[type]
-        return [defOperator as (CmpNode | EqualNode)[0], field.name, defValue]
+        return [defOperator as (CmpNode | EqualNode)[0], field.name, defaultValue(field)]
     }
 }
 
-
 // export const fieldOperator = {
 //     Str: { ...cmpObj, ...likeObj },
 //     Int: cmpObj,
@@ -138,75 +142,46 @@ export function createOperationNode(field: FieldInfo, operationObjs: ObjStr[]):
 //     | "currentTime"
 //     | "currentDate"
 
-type Value = typeof defalueValue[TypeKey]
-    // | { type: "Int", value: number }
-    // | { type: "Num", value: number }
-    // | { type: "Str", value: string }
-    // | { type: "DateTime", value: string }
-    // | { type: "UnixTimestamp", value: number }
-    // | { type: "CurrentUserId" | "CurrentTime" | "CurrentDate" }
-    // | { [K in TypeKey]: { type: K, value: TypeMap[K] } }[keyof TypeMap]
-
-
-
-export type TypeMap = typeof defalueValue[TypeKey]
-// {
-//     Int: number,
-//     Num: number,
-//     Str: string,
-//     DateTime: string,
-//     UnixTimestamp: number,
-// }
-
-export type TypeKey = keyof typeof defalueValue
-
-// type ListValue = { [K in TypeKey]: { type: K, value: TypeMap[K][] } }[keyof TypeMap]
-// type ListValue =
-//     | { type: "Str", value: string[] }
-//     | { type: "Int", value: number[] }
-//     | { type: "Num", value: number[] }
-//     | { type: "DateTime", value: string[] }
-//     | { type: "UnixTimestamp", value: number[] }
-type ListValue = _ListValue<Value>
-type _ListValue<Val> = Val extends { type: infer T, value: infer V } ? { type: T, value: V[] } : never
-// type ListValue = ObjAssign<Value, { value: 1}>
-
-// type ValueListType<T extends FieldType> = TypeMap<T, ListValue>
-
-// type TypeMap<T extends FieldType, Val>
-//     = T extends object ? string
-//     : Val extends { type: T, value: infer V } ? V
-//     : never
-
-type FieldName = string & {}
+/** 有字面量值的 `Value` 类型，和 `ListValue` 一一对应 */
+export type ScalarType = ListValue["type"]
 
-// type ListNode =
-//     | { strList: string[] }
-//     | { intList: number[] }
-//     | { numList: number[] }
-
-export type FieldType = TypeKey | object
-export const defalueValue = {
+export const defalueValue: { readonly [K in ScalarType]: Extract<Value, { type: K }> } = {
+    get Id() { return { type: "Id" as const, value: 0 } },
     get Int() { return { type: "Int" as const, value: 0 } },
     get Num() { return { type: "Num" as const, value: 0 } },
+    get Decimal() { return { type: "Decimal" as const, value: "0" } },
     get Str() { return { type: "Str" as const, value: "" } },
     get DateTime() { return { type: "DateTime" as const, value: "" } },
-    get UnixTimestamp() { return { type: "UnixTimestamp" as const, value: 0 } },
-}
-// export const inputType = {
-//     Int: "number",
-//     Num: "number",
-//     Str: "text",
-//     DateTime: "date",
-//     UnixTimestamp: () { return { type: "UnixTimestamp" as const, value: 0 } },
-// }
-
-export interface FieldInfo {
-    name: FieldName,
-    text: string,
-    type: FieldType,
-    nullable?: true,
-    operation: string,
+    get UnixTiemstamp() { return { type: "UnixTiemstamp" as const, value: 0 } },
+}
+
+/** 字段值的类型，枚举按字符串传值，金额字段用字符串保留精度 */
+export function valueType(type: FieldType): ScalarType {
+    return typeof type == "string" ? type : "Enum" in type ? "Str" : "Decimal"
+}
+
+export function inputType(type: FieldType): "text" | "number" {
+    const value = defalueValue[valueType(type)].value
+    return typeof value == "number" ? "number" : "text"
+}
+
+/** 枚举字段默认选第一项 */
+export function defaultValue(field: FieldInfo): Value {
+    const labels = enumLabels(field)
+    return labels ? { type: "Str", value: Object.keys(labels)[0] } : defalueValue[valueType(field.type_)]
+}
+
+export function emptyList(type: FieldType): ListValue {
+    return { type: valueType(type), value: [] } as ListValue
+}
+
+export function enumLabels(field: FieldInfo): Record<string, string> | undefined {
+    return typeof field.type_ == "object" && "Enum" in field.type_ ? field.labels ?? {} : undefined
+}
+
+/** 服务端的字段信息，枚举字段附带每个取值的显示文本 */
+export interface FieldInfo extends ServerFieldInfo {
+    labels?: Record<string, string>,
 }
 
 // export type FieldInfos = Record<FieldName, FieldInfo>
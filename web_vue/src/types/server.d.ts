@@ -0,0 +1,113 @@
+// 由 server_rust `cargo run --bin gen_ts` 生成，不要手动修改
+
+export type FieldName = string
+export type Logical = "And" | "Or"
+export type Not = "Not"
+export type Nullable = "IsNull" | "IsNotNull"
+export type Equal = "Eq" | "NotEq"
+export type Cmp = "Gt" | "GtEq" | "Lt" | "LtEq"
+export type Between = "Between" | "GtEqLt" | "GtLtEq"
+export type In = "In" | "NotIn"
+export type Like = "StartWith" | "Contains" | "EndWith"
+export type TimeUnit = "Second" | "Minute" | "Hour" | "Day" | "Week" | "Month" | "Year"
+export interface RelativeTime {
+    offset: number,
+    unit: TimeUnit,
+    trunc?: TimeUnit,
+}
+
+export type Value =
+    | { type: "Id", value: number }
+    | { type: "Int", value: number }
+    | { type: "Num", value: number }
+    | { type: "Decimal", value: string }
+    | { type: "Str", value: string }
+    | { type: "DateTime", value: string }
+    | { type: "UnixTiemstamp", value: number }
+    | { type: "CurrentUserId" }
+    | { type: "CurrentDate" }
+    | { type: "CurrentTime" }
+    | { type: "RelativeTime", value: RelativeTime }
+    | { type: "Field", value: string }
+export type ListValue =
+    | { type: "Id", value: number[] }
+    | { type: "Int", value: number[] }
+    | { type: "Num", value: number[] }
+    | { type: "Decimal", value: string[] }
+    | { type: "Str", value: string[] }
+    | { type: "DateTime", value: string[] }
+    | { type: "UnixTiemstamp", value: number[] }
+export type FieldType =
+    | "Str"
+    | "Int"
+    | "Num"
+    | { Decimal: { scale: number } }
+    | "DateTime"
+    | "UnixTiemstamp"
+    | "Id"
+    | { Enum: string }
+export type FilterNode =
+    | [Logical, FilterNode[]]
+    | [Not, FilterNode]
+    | [Nullable, FieldName]
+    | [Equal, FieldName, Value]
+    | [Cmp, FieldName, Value]
+    | [Between, FieldName, Value, Value]
+    | [In, FieldName, ListValue]
+    | [Like, FieldName, string]
+
+export interface FieldInfo {
+    name: FieldName,
+    text: string,
+    nullable: boolean,
+    type_: FieldType,
+    operation: string,
+    sortable?: boolean,
+    aggregatable?: boolean,
+}
+export interface VersionedFilter {
+    version: number,
+    filter: FilterNode,
+}
+
+export type Direction = "Asc" | "Desc"
+export type Nulls = "First" | "Last"
+export interface SortItem {
+    field: FieldName,
+    direction?: Direction,
+    nulls?: Nulls,
+}
+export type SortSpec = SortItem[]
+export type AggFn = "Count" | "Sum" | "Avg" | "Min" | "Max"
+export interface Aggregate {
+    func: AggFn,
+    alias: string,
+    field?: FieldName,
+}
+
+export type FnDisplay = "Show" | "Disable"
+export interface FnFlag {
+    id: number,
+    parent_id: number,
+    seq: number,
+    flag: number,
+    key: FnFlagKey | "",
+    text: string,
+    display: FnDisplay,
+}
+export type FnFlagKey = "user_management" | "wx_user:disable" | "wx_user:show" | "set_user_tag:disable" | "set_user_tag:show"
+export type ApiKey = "wx_user/get_list" | "wx_user/set_user_tag" | "wx_user/get_detail" | "wx_user/set_remark" | "user_tag/get_detail" | "user_tag/add" | "user_tag/export" | "user_tag/update"
+/** 用户权限，seq => 功能标记位 */
+export type UserPermission = Record<number, number>
+
+export type Visibility = "Private" | "Global" | { Role: number }
+export interface SavedView {
+    id: number,
+    page: string,
+    name: string,
+    owner_id: number,
+    visibility: Visibility,
+    filter: VersionedFilter,
+    sort: SortSpec,
+    revision: number,
+}